@@ -0,0 +1,310 @@
+use std::fs;
+use std::env;
+use serde_json::{Value, Map};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ConfigMap;
+use themes::*;
+use themes::default::default_theme;
+use themes::color::{ColorDepth, COLOR_DEPTH_NAMES, color_depth_from_env};
+use themes::file::{get_theme_file, list_theme_files, load_theme_file, color_from_json};
+use prompt::ColorOverride;
+use layout::default_priority;
+
+pub const DEFAULT_CONFIG:&str = r###"
+{
+    "segments": [
+        "username",
+        "hostname",
+        "read_only",
+        "exit_code",
+        "cwd",
+        "git",
+        "root"
+    ]
+}
+"###;
+
+const WORKING_DIR_CF: &str = "prompter.json";
+const HOME_DIR_CF: &str    = "/.prompter.json";
+const STD_DIR_CF: &str     = "/prompter/config.json";
+const THEME_DIR: &str      = "/prompter/themes";
+const SCRIPT_DIR: &str     = "/prompter/scripts";
+const ERR_MSG: &str = "Unable to read file";
+
+pub fn fetch_config_as_string() -> String {
+    if Path::new(WORKING_DIR_CF).exists() {
+        return fs::read_to_string(WORKING_DIR_CF).expect(ERR_MSG);
+    }
+
+    let mut home_file = String::new();
+    
+    if let Ok(home_var) = env::var("HOME") {
+        home_file.push_str(&home_var);
+    } else {
+        eprintln!("HOME variable not set. Something is very broken here.");
+        return String::from(DEFAULT_CONFIG);
+    }
+        
+    home_file.push_str(HOME_DIR_CF);
+
+    if Path::new(&home_file).exists() {
+        return fs::read_to_string(&home_file).expect(ERR_MSG);
+    }
+
+    let mut fallback = get_config_home().expect("HOME not set. Get a life.");
+    fallback.push_str(STD_DIR_CF);
+
+    if Path::new(&fallback).exists() {
+        return fs::read_to_string(&fallback).expect(ERR_MSG);
+    } else {
+        return String::from(DEFAULT_CONFIG);
+    }
+}
+
+// XDG_CONFIG_HOME, falling back to $HOME/.config
+pub fn get_config_home() -> Option<String> {
+    if let Ok(xdg) = env::var("XDG_CONFIG_HOME") {
+        return Some(xdg);
+    }
+
+    if let Ok(home_var) = env::var("HOME") {
+        return Some(format!("{}/.config", home_var));
+    }
+    return None;
+}
+
+pub fn get_theme_dir() -> Option<PathBuf> {
+    get_config_home().map(|config_home| PathBuf::from(config_home + THEME_DIR))
+}
+
+// relative script files of script segments are looked up here
+pub fn get_script_dir() -> Option<PathBuf> {
+    get_config_home().map(|config_home| PathBuf::from(config_home + SCRIPT_DIR))
+}
+
+pub fn get_config() -> ConfigMap {
+    let config_source = fetch_config_as_string();
+
+    match serde_json::from_str(&config_source) {
+        Ok(config) => return config,
+        Err(err) => {
+            eprintln!("Configfile broken: {}", err);
+            return HashMap::new();
+        }
+    }
+}
+
+// user theme files take precedence over the built-in themes
+pub fn get_theme(map: &ConfigMap) -> Theme {
+    if let Some(Value::String(name)) = map.get("theme") {
+        if let Some(path) = get_theme_dir().and_then(|dir| get_theme_file(&dir, name)) {
+            match load_theme_file(&path) {
+                Ok(theme) => return theme,
+                Err(reason) => {
+                    eprintln!("Error: {}", reason);
+                    return default_theme();
+                }
+            }
+        }
+
+        if let Some(theme) = get_theme_for_name(name) {
+            return theme;
+        }
+
+        eprintln!("Unknown theme: {}, using default. Available themes: {}", name, get_theme_names().join(", "));
+    }
+    return default_theme();
+}
+
+pub fn get_theme_names() -> Vec<String> {
+    let mut names: Vec<String> = THEMES.iter().map(|(name, _)| name.to_string()).collect();
+
+    if let Some(dir) = get_theme_dir() {
+        for name in list_theme_files(&dir) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    return names;
+}
+
+pub fn get_symbols(map: &ConfigMap) -> Symbols {
+    let mut symbols = get_symbolset_for_name("patched");
+
+    if let Some(Value::String(mode)) = map.get("mode") {
+        if !SYMBOL_MODES.contains(&mode.to_lowercase().as_str()) {
+            eprintln!("Unknown mode: {}, using patched. Available modes: {}", mode, SYMBOL_MODES.join(", "));
+        }
+        symbols = get_symbolset_for_name(&mode.to_lowercase());
+    }
+
+    // single symbols of the mode could be replaced
+    if let Some(Value::Object(overrides)) = map.get("symbols") {
+        for (key, value) in overrides {
+            let symbol = match value.as_str() {
+                Some(s) => s.to_string(),
+                None => {
+                    eprintln!("Symbol {} needs to be a string, got: {}", key, value);
+                    continue;
+                }
+            };
+
+            match key.as_str() {
+                "lock"           => symbols.lock = symbol,
+                "network"        => symbols.network = symbol,
+                "vcs"            => symbols.vcs = symbol,
+                "separator"      => symbols.separator = symbol,
+                "separator_thin" => symbols.separator_thin = symbol,
+                "separator_right" => symbols.separator_right = symbol,
+                "separator_right_thin" => symbols.separator_right_thin = symbol,
+                _                => eprintln!("Unknown symbol: {}", key),
+            }
+        }
+    }
+    return symbols;
+}
+
+// the color_depth key of the config wins over the terminal detection
+pub fn get_color_depth(map: &ConfigMap) -> ColorDepth {
+    if let Some(value) = map.get("color_depth") {
+        let name = match value {
+            Value::String(s) => s.clone(),
+            _ => value.to_string(),
+        };
+
+        match ColorDepth::from_name(&name) {
+            Some(depth) => return depth,
+            None => eprintln!("Unknown color_depth: {}, use one of: {}", name, COLOR_DEPTH_NAMES.join(", ")),
+        }
+    }
+    return color_depth_from_env();
+}
+
+// segments also configured in a block of another name, as in powerline-shell
+const CONFIG_BLOCKS: [(&str, &str); 1] = [
+    ("git", "vcs"),
+];
+
+// the names of the config blocks of a segment: its own one and the one of
+// another name, which takes precedence
+fn config_blocks(name: &str) -> Vec<&str> {
+    let mut blocks = vec![name];
+
+    if let Some((_, block)) = CONFIG_BLOCKS.iter().find(|(segment, _)| *segment == name.to_lowercase()) {
+        blocks.push(block);
+    }
+    return blocks;
+}
+
+// the config blocks of a segment which are objects
+fn block_objects<'a>(map: &'a ConfigMap, name: &str) -> Vec<&'a Map<String, Value>> {
+    return config_blocks(name).into_iter()
+        .filter_map(|block| match map.get(block) {
+            Some(Value::Object(block)) => Some(block),
+            _ => None,
+        })
+        .collect();
+}
+
+// the options of a segment out of its config blocks
+pub fn get_cwd_config(map: &ConfigMap, name: &str) -> Option<ConfigMap> {
+    let mut cwd_config: Option<ConfigMap> = None;
+
+    for block in config_blocks(name) {
+        match map.get(block) {
+            Some(Value::Object(options)) => cwd_config.get_or_insert_with(ConfigMap::new).extend(options.clone()),
+            Some(value) => eprintln!("Config block {} needs to be an object, got: {}", block, value),
+            None => (),
+        }
+    }
+    return cwd_config;
+}
+
+// colors from the named config block of a segment (e.g. "hostname": {...}),
+// the keys of a segment object take precedence
+pub fn get_color_override(map: &ConfigMap, name: &str, segment_object: Option<&Map<String, Value>>) -> ColorOverride {
+    let mut colors = ColorOverride::default();
+
+    let mut sources: Vec<&Map<String, Value>> = block_objects(map, name);
+
+    if let Some(o) = segment_object {
+        sources.push(o);
+    }
+
+    for source in sources {
+        for (key, value) in source {
+            let slot = match key.as_str() {
+                "fg_color"        => &mut colors.fg,
+                "bg_color"        => &mut colors.bg,
+                "separator_color" => &mut colors.separator,
+                _                 => continue,
+            };
+
+            match color_from_json(value) {
+                Some(code) => *slot = Some(code),
+                None => eprintln!("Color {} of segment {} is not a color code: {}", key, name, value),
+            }
+        }
+    }
+    return colors;
+}
+
+// the "priority" key of the named config block or of the segment object
+pub fn get_priority(map: &ConfigMap, name: &str, segment_object: Option<&Map<String, Value>>) -> i64 {
+    let mut priority = default_priority(name);
+
+    for source in block_objects(map, name).into_iter().chain(segment_object) {
+        if let Some(value) = source.get("priority") {
+            match value.as_i64() {
+                Some(p) => priority = p,
+                None => eprintln!("Priority of segment {} is not a number: {}", name, value),
+            }
+        }
+    }
+    return priority;
+}
+
+// "timeout" in milliseconds and "timeout_placeholder" of the config, the named
+// config block or the segment object. Without timeout the prompt waits for the
+// segment, without placeholder a segment running into its timeout is hidden.
+pub fn get_timeout(map: &ConfigMap, name: &str, segment_object: Option<&Map<String, Value>>) -> (Option<Duration>, Option<String>) {
+    let mut timeout = None;
+    let mut placeholder = None;
+
+    let global = (map.get("timeout"), map.get("timeout_placeholder"));
+    let sources = block_objects(map, name).into_iter().chain(segment_object).map(|source| (source.get("timeout"), source.get("timeout_placeholder")));
+
+    for (value, text) in Some(global).into_iter().chain(sources) {
+        if let Some(value) = value {
+            match value.as_u64() {
+                Some(ms) => timeout = Some(Duration::from_millis(ms)),
+                None => eprintln!("Timeout of segment {} is not a number of milliseconds: {}", name, value),
+            }
+        }
+
+        if let Some(text) = text {
+            match text.as_str() {
+                Some(text) => placeholder = Some(text.to_string()),
+                None => eprintln!("Timeout placeholder of segment {} is not a string: {}", name, text),
+            }
+        }
+    }
+    return (timeout, placeholder);
+}
+
+// columns the prompt may take, "max_width" is the fraction of the terminal width
+pub fn get_max_width(map: &ConfigMap, columns: Option<usize>) -> Option<usize> {
+    let mut fraction = 1.0;
+
+    if let Some(value) = map.get("max_width") {
+        match value.as_f64() {
+            Some(f) if f > 0.0 && f <= 1.0 => fraction = f,
+            _ => eprintln!("max_width needs to be a number between 0 and 1, got: {}", value),
+        }
+    }
+
+    return columns.map(|columns| (columns as f64 * fraction) as usize);
+}
@@ -0,0 +1,720 @@
+// Caches the output of expensive commands, like git status, per directory.
+// `prompter daemon` listens on a unix socket of the user, the segments ask it
+// for the output and run the command themselves if no daemon is running.
+// Cached output is dropped as soon as inotify reports a change in the
+// directory, or anywhere in the worktree of a repository apart from ignored
+// paths, and in the parts of its git directories that git status reads.
+// Directories not asked for in a while are no longer watched.
+
+extern crate libc;
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{Value, Map};
+use git2::Repository;
+use segments::git::{find_repository, find_common_dir};
+
+// the segment timeout decides how long the prompt waits, this only keeps a
+// stuck daemon from piling up threads
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// variables of the client passed on with a request, along with all GIT_*
+// ones; the rest of the environment is the one of the daemon
+const CLIENT_ENV: [&str; 6] = ["PATH", "HOME", "LANG", "LC_ALL", "LC_MESSAGES", "XDG_CONFIG_HOME"];
+
+// roots not asked for in a while are dropped with their watches, each
+// directory of a worktree takes one of the limited inotify watches
+const MAX_ROOTS: usize = 32;
+const ROOT_IDLE: Duration = Duration::from_secs(60 * 60);
+
+const WATCH_MASK: u32 = libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_CLOSE_WRITE | libc::IN_CREATE | libc::IN_DELETE
+    | libc::IN_MOVED_FROM | libc::IN_MOVED_TO | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+}
+
+// a command, run in a directory with some environment variables, all of them
+// make up the key of the cache
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Request {
+    pub cwd: PathBuf,
+    pub command: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+struct Root {
+    // bumped on every change, output computed meanwhile is not cached
+    generation: u64,
+    // false if not all directories could be watched, nothing is cached then
+    // and the next request walks the root again
+    watched: bool,
+    used: Instant,
+}
+
+// a watched directory of a root, with its subdirectories if recursive
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Watch {
+    root: PathBuf,
+    dir: PathBuf,
+    recursive: bool,
+    // one of the directories of the watch plan, not found by the walk
+    top: bool,
+}
+
+#[derive(Default)]
+struct State {
+    cache: HashMap<Request, (PathBuf, CommandOutput)>,
+    roots: HashMap<PathBuf, Root>,
+    // roots share descriptors, e.g. linked worktrees the common git directory
+    watches: HashMap<i32, Vec<Watch>>,
+}
+
+impl State {
+    fn insert_watch(&mut self, wd: i32, watch: Watch) {
+        let watches = self.watches.entry(wd).or_default();
+
+        if !watches.contains(&watch) {
+            watches.push(watch);
+        }
+    }
+}
+
+impl Request {
+    // the environment of the client, with env on top
+    pub fn new(cwd: &Path, command: &[&str], env: &[(&str, &str)]) -> Request {
+        let mut vars: Vec<(String, String)> = env::vars()
+            .filter(|(key, _)| is_client_var(key) && !env.iter().any(|(name, _)| name == key))
+            .chain(env.iter().map(|(key, value)| (key.to_string(), value.to_string())))
+            .collect();
+        vars.sort();
+
+        Request {cwd: cwd.to_path_buf(),
+                 command: command.iter().map(|arg| arg.to_string()).collect(),
+                 env: vars}
+    }
+
+    fn to_json(&self) -> Value {
+        let env: Map<String, Value> = self.env.iter().map(|(key, value)| (key.clone(), Value::from(value.as_str()))).collect();
+
+        json!({"cwd": self.cwd.display().to_string(), "command": self.command, "env": env})
+    }
+
+    fn from_json(value: &Value) -> Option<Request> {
+        let cwd = PathBuf::from(value.get("cwd")?.as_str()?);
+        let command: Vec<String> = value.get("command")?.as_array()?.iter()
+            .filter_map(|arg| arg.as_str().map(String::from))
+            .collect();
+        let mut env: Vec<(String, String)> = value.get("env")?.as_object()?.iter()
+            .filter_map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string())))
+            .collect();
+
+        // the key of the cache
+        env.sort();
+
+        if command.is_empty() {
+            return None;
+        }
+        return Some(Request {cwd, command, env});
+    }
+
+    // Runs the command. The client variables of the daemon not set by the
+    // client are dropped. git must not refresh the index, the change would
+    // invalidate the output right away.
+    pub fn run(&self) -> io::Result<CommandOutput> {
+        let mut command = Command::new(&self.command[0]);
+
+        for (key, _) in env::vars().filter(|(key, _)| is_client_var(key)) {
+            command.env_remove(key);
+        }
+
+        let output = command
+            .args(&self.command[1..])
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .env("GIT_OPTIONAL_LOCKS", "0")
+            .current_dir(&self.cwd)
+            .output()?;
+
+        Ok(CommandOutput {success: output.status.success(), stdout: String::from_utf8_lossy(&output.stdout).into_owned()})
+    }
+}
+
+fn is_client_var(key: &str) -> bool {
+    key.starts_with("GIT_") || CLIENT_ENV.contains(&key)
+}
+
+// $XDG_RUNTIME_DIR is private to the user, /tmp is shared
+#[cfg(not(test))]
+pub fn socket_path() -> PathBuf {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("prompter.sock"),
+        _ => PathBuf::from(format!("/tmp/prompter-{}.sock", unsafe { libc::getuid() })),
+    }
+}
+
+// tests do not talk to the daemon of the user
+#[cfg(test)]
+pub fn socket_path() -> PathBuf {
+    env::temp_dir().join(format!("prompter-test-{}.sock", std::process::id()))
+}
+
+// runs the command in the directory, through the daemon if one is running
+pub fn cached_output(cwd: &Path, command: &[&str], env: &[(&str, &str)]) -> io::Result<CommandOutput> {
+    let request = Request::new(cwd, command, env);
+
+    if let Some(output) = query(&socket_path(), &request) {
+        return Ok(output);
+    }
+    return request.run();
+}
+
+// true if a daemon of the user listens on the socket
+pub fn is_running(path: &Path) -> bool {
+    return connect(path).is_some();
+}
+
+fn connect(path: &Path) -> Option<UnixStream> {
+    // a socket of someone else could show anything in the prompt
+    if fs::metadata(path).ok()?.uid() != unsafe { libc::getuid() } {
+        return None;
+    }
+    return UnixStream::connect(path).ok();
+}
+
+pub fn query(path: &Path, request: &Request) -> Option<CommandOutput> {
+    let mut stream = connect(path)?;
+    stream.set_read_timeout(Some(QUERY_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(QUERY_TIMEOUT)).ok()?;
+
+    writeln!(stream, "{}", request.to_json()).ok()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+
+    let response: Value = serde_json::from_str(&line).ok()?;
+
+    if let Some(error) = response.get("error") {
+        eprintln!("prompter daemon: {}", error);
+        return None;
+    }
+
+    Some(CommandOutput {success: response.get("success")?.as_bool()?,
+                        stdout: response.get("stdout")?.as_str()?.to_string()})
+}
+
+pub fn serve(path: &Path) -> Result<(), String> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(format!("already running on {}", path.display()));
+    }
+
+    // left over from a daemon that is gone
+    let _ = fs::remove_file(path);
+
+    let listener = UnixListener::bind(path).map_err(|err| format!("unable to listen on {}: {}", path.display(), err))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|err| err.to_string())?;
+
+    let inotify = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if inotify < 0 {
+        return Err(format!("inotify: {}", io::Error::last_os_error()));
+    }
+
+    let state = Arc::new(Mutex::new(State::default()));
+
+    let watcher_state = Arc::clone(&state);
+    thread::spawn(move || watch(inotify, &watcher_state));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = Arc::clone(&state);
+                thread::spawn(move || handle(stream, inotify, &state));
+            },
+            Err(err) => eprintln!("prompter daemon: {}", err),
+        }
+    }
+    return Ok(());
+}
+
+fn handle(stream: UnixStream, inotify: i32, state: &Mutex<State>) {
+    let _ = stream.set_read_timeout(Some(QUERY_TIMEOUT));
+
+    let mut line = String::new();
+    if BufReader::new(&stream).read_line(&mut line).is_err() {
+        return;
+    }
+
+    let response = match serde_json::from_str(&line).ok().as_ref().and_then(Request::from_json) {
+        Some(request) => match lookup(&request, inotify, state) {
+            Ok(output) => json!({"success": output.success, "stdout": output.stdout}),
+            Err(err) => json!({"error": err.to_string()}),
+        },
+        None => json!({"error": "invalid request"}),
+    };
+
+    let mut stream = stream;
+    let _ = writeln!(stream, "{}", response);
+}
+
+fn lookup(request: &Request, inotify: i32, state: &Mutex<State>) -> io::Result<CommandOutput> {
+    let (root, dirs) = watch_plan(request);
+
+    let known = {
+        let mut state = state.lock().unwrap();
+        let cached = state.cache.get(request).map(|(_, output)| output.clone());
+
+        match state.roots.get_mut(&root) {
+            Some(r) if r.watched => r.used = Instant::now(),
+            // the partial watches of a failed walk are dropped before the next one
+            Some(_) => forget_root(inotify, &mut state, &root),
+            None => (),
+        }
+
+        if let Some(output) = cached {
+            return Ok(output);
+        }
+        state.roots.contains_key(&root)
+    };
+
+    // walked without the lock, a big worktree takes a while and other clients
+    // would wait for it; inotify hands out the same descriptors to a second walk
+    if !known {
+        evict(inotify, &mut state.lock().unwrap());
+
+        let mut watches = Vec::new();
+        let ignored = ignored_paths(&root);
+        let watched = dirs.iter().all(|(dir, recursive)| add_watches(inotify, dir, *recursive, &*ignored, &mut watches));
+
+        let mut state = state.lock().unwrap();
+
+        for (wd, dir, recursive) in watches {
+            let top = dirs.iter().any(|(planned, _)| *planned == dir);
+            state.insert_watch(wd, Watch {root: root.clone(), dir, recursive, top});
+        }
+        state.roots.entry(root.clone()).or_insert(Root {generation: 0, watched, used: Instant::now()});
+    }
+
+    // the root is gone again if its ignore rules changed meanwhile
+    let generation = state.lock().unwrap().roots.get(&root).map_or(0, |r| r.generation);
+
+    let output = request.run()?;
+
+    let mut state = state.lock().unwrap();
+    let unchanged = state.roots.get(&root).is_some_and(|r| r.watched && r.generation == generation);
+
+    if unchanged {
+        state.cache.insert(request.clone(), (root, output.clone()));
+    }
+    return Ok(output);
+}
+
+// The root of a request and the directories to watch for it: for a repository
+// the whole worktree, HEAD and the index in the git directory, and the refs,
+// packed-refs and reflogs in the common one. Otherwise just the directory.
+fn watch_plan(request: &Request) -> (PathBuf, Vec<(PathBuf, bool)>) {
+    let var = |name: &str| request.env.iter().find(|(key, _)| key == name).map(|(_, value)| request.cwd.join(value));
+
+    let repository = match var("GIT_DIR") {
+        Some(git_dir) => Some((var("GIT_WORK_TREE").unwrap_or_else(|| request.cwd.clone()), git_dir)),
+        None => find_repository(&request.cwd),
+    };
+
+    let (worktree, git_dir) = match repository {
+        Some(repository) => repository,
+        None => return (request.cwd.clone(), vec![(request.cwd.clone(), false)]),
+    };
+
+    let common_dir = find_common_dir(&git_dir);
+
+    let mut dirs = vec![(worktree.clone(), true), (git_dir.clone(), false)];
+
+    if common_dir != git_dir {
+        dirs.push((common_dir.clone(), false));
+    }
+
+    dirs.push((common_dir.join("refs"), true));
+
+    if common_dir.join("logs").is_dir() {
+        dirs.push((common_dir.join("logs"), true));
+    }
+    return (worktree, dirs);
+}
+
+// Paths of the worktree ignored by git, e.g. target/ and node_modules/. A build
+// there would drop the cache all the time, and large ones use up the inotify
+// watches of the user.
+fn ignored_paths(root: &Path) -> Box<dyn Fn(&Path) -> bool> {
+    let repo = Repository::open(root).ok();
+    let root = root.to_path_buf();
+
+    Box::new(move |path: &Path| {
+        let relative = match path.strip_prefix(&root) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+
+        // libgit2 counts the git directory as ignored
+        if relative.components().any(|component| component.as_os_str() == ".git") {
+            return false;
+        }
+        repo.as_ref().is_some_and(|repo| repo.is_path_ignored(relative).unwrap_or(false))
+    })
+}
+
+fn add_watches(inotify: i32, dir: &Path, recursive: bool, ignored: &dyn Fn(&Path) -> bool, watches: &mut Vec<(i32, PathBuf, bool)>) -> bool {
+    let c_dir = match CString::new(dir.as_os_str().as_bytes()) {
+        Ok(c_dir) => c_dir,
+        Err(_) => return false,
+    };
+
+    let wd = unsafe { libc::inotify_add_watch(inotify, c_dir.as_ptr(), WATCH_MASK) };
+    if wd < 0 {
+        eprintln!("prompter daemon: unable to watch {}: {}", dir.display(), io::Error::last_os_error());
+        return false;
+    }
+    watches.push((wd, dir.to_path_buf(), recursive));
+
+    if !recursive {
+        return true;
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    for entry in entries.flatten() {
+        // symlinks are not followed
+        if !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            continue;
+        }
+
+        let path = entry.path();
+
+        // the git directories are watched on their own, objects only change
+        // together with the index or a ref
+        if path.ends_with(".git") || ignored(&path) {
+            continue;
+        }
+
+        if !add_watches(inotify, &path, true, ignored, watches) {
+            return false;
+        }
+    }
+    return true;
+}
+
+fn watch(inotify: i32, shared: &Mutex<State>) {
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let length = unsafe { libc::read(inotify, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+
+        if length <= 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            eprintln!("prompter daemon: inotify: {}", io::Error::last_os_error());
+            return;
+        }
+
+        // new directories of a worktree or of the refs, with their root
+        let mut created: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+        let mut state = shared.lock().unwrap();
+        let mut offset = 0;
+
+        // struct inotify_event: wd, mask, cookie, len and the name, padded with NULs
+        while offset + 16 <= length as usize {
+            let field = |at: usize| [buffer[offset + at], buffer[offset + at + 1], buffer[offset + at + 2], buffer[offset + at + 3]];
+
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(4));
+            let name_length = u32::from_ne_bytes(field(12)) as usize;
+
+            let name = &buffer[offset + 16..offset + 16 + name_length];
+            let name = Path::new(std::ffi::OsStr::from_bytes(name.split(|&b| b == 0).next().unwrap_or(&[])));
+
+            offset += 16 + name_length;
+
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                state.cache.clear();
+                for root in state.roots.values_mut() {
+                    root.generation += 1;
+                }
+                continue;
+            }
+
+            let watches = match state.watches.get(&wd) {
+                Some(watches) => watches.clone(),
+                None => continue,
+            };
+
+            if mask & libc::IN_IGNORED != 0 {
+                state.watches.remove(&wd);
+            }
+
+            for watch in watches {
+                let root = watch.root;
+
+                // different directories are ignored now, the next request walks the root again
+                if name == Path::new(".gitignore") {
+                    forget_root(inotify, &mut state, &root);
+                    continue;
+                }
+
+                // the worktree or a git directory was deleted or moved away, one
+                // created in its place is not watched
+                if watch.top && mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_IGNORED) != 0 {
+                    forget_root(inotify, &mut state, &root);
+                    continue;
+                }
+
+                state.cache.retain(|_, (cached_root, _)| *cached_root != root);
+
+                if let Some(r) = state.roots.get_mut(&root) {
+                    r.generation += 1;
+
+                    // the paths of the directories below are wrong now
+                    if mask & libc::IN_MOVE_SELF != 0 {
+                        r.watched = false;
+                    }
+                }
+
+                if watch.recursive && mask & libc::IN_ISDIR != 0 && mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                    created.push((root, watch.dir.join(name)));
+                }
+            }
+        }
+
+        drop(state);
+
+        // walked without the lock, like in lookup
+        watch_created(inotify, shared, created);
+    }
+}
+
+// watches new directories of the roots and their subdirectories
+fn watch_created(inotify: i32, state: &Mutex<State>, created: Vec<(PathBuf, PathBuf)>) {
+    for (root, path) in created {
+        let ignored = ignored_paths(&root);
+
+        if ignored(&path) {
+            continue;
+        }
+
+        let mut added = Vec::new();
+        let watched = add_watches(inotify, &path, true, &*ignored, &mut added);
+
+        let mut state = state.lock().unwrap();
+
+        match state.roots.get_mut(&root) {
+            // output computed during the walk may miss changes in the new directories
+            Some(r) => {
+                r.generation += 1;
+                r.watched &= watched;
+            },
+            // forgotten meanwhile
+            None => {
+                for (wd, _, _) in added {
+                    if !state.watches.contains_key(&wd) {
+                        unsafe { libc::inotify_rm_watch(inotify, wd) };
+                    }
+                }
+                continue;
+            },
+        }
+
+        state.cache.retain(|_, (cached_root, _)| *cached_root != root);
+
+        for (wd, dir, recursive) in added {
+            state.insert_watch(wd, Watch {root: root.clone(), dir, recursive, top: false});
+        }
+    }
+}
+
+// drops roots not asked for in a while, and the least recently used ones to
+// make room for a new root
+fn evict(inotify: i32, state: &mut State) {
+    let mut roots: Vec<(PathBuf, Instant)> = state.roots.iter().map(|(root, r)| (root.clone(), r.used)).collect();
+    roots.sort_by_key(|(_, used)| *used);
+
+    let excess = (roots.len() + 1).saturating_sub(MAX_ROOTS);
+
+    for (idx, (root, used)) in roots.iter().enumerate() {
+        if idx < excess || used.elapsed() > ROOT_IDLE {
+            forget_root(inotify, state, root);
+        }
+    }
+}
+
+// drops the root with its cached output and watches, unless other roots share them
+fn forget_root(inotify: i32, state: &mut State, root: &Path) {
+    state.roots.remove(root);
+    state.cache.retain(|_, (cached_root, _)| cached_root != root);
+
+    let mut unused = Vec::new();
+
+    for (wd, watches) in state.watches.iter_mut() {
+        watches.retain(|watch| watch.root != root);
+
+        if watches.is_empty() {
+            unused.push(*wd);
+        }
+    }
+
+    for wd in unused {
+        state.watches.remove(&wd);
+        unsafe { libc::inotify_rm_watch(inotify, wd) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Instant;
+
+    // asks until the output is a different one, for at most five seconds
+    fn changes(socket: &Path, request: &Request, before: &CommandOutput) -> bool {
+        let start = Instant::now();
+
+        while start.elapsed() < Duration::from_secs(5) {
+            if query(socket, request).as_ref() != Some(before) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        return false;
+    }
+
+    #[test]
+    fn test_daemon_cache() {
+        let dir = env::temp_dir().join(format!("prompter-daemon-test-{}", std::process::id()));
+        let socket = dir.join("prompter.sock");
+        let worktree = dir.join("worktree");
+        fs::create_dir_all(&worktree).unwrap();
+
+        let server_socket = socket.clone();
+        thread::spawn(move || serve(&server_socket));
+
+        // the pid of the shell tells whether the command ran again
+        let request = Request {cwd: worktree.clone(), command: vec![String::from("sh"), String::from("-c"), String::from("echo $$")], env: Vec::new()};
+
+        let start = Instant::now();
+        let mut first = query(&socket, &request);
+        while first.is_none() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+            first = query(&socket, &request);
+        }
+
+        let first = first.expect("daemon not running");
+        assert!(first.success);
+        assert_eq!(query(&socket, &request), Some(first.clone()));
+
+        fs::write(worktree.join("changed"), "").unwrap();
+        assert!(changes(&socket, &request, &first));
+
+        // a directory created in place of a deleted one is watched again
+        let before = query(&socket, &request).expect("daemon not running");
+        fs::remove_dir_all(&worktree).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+        assert!(changes(&socket, &request, &before));
+
+        let after = query(&socket, &request).expect("daemon not running");
+        fs::write(worktree.join("new"), "").unwrap();
+        assert!(changes(&socket, &request, &after));
+
+        // the environment is part of the key
+        let git_dir = |value: &str| Request {cwd: worktree.clone(),
+                                             command: vec![String::from("sh"), String::from("-c"), String::from("echo $GIT_DIR")],
+                                             env: vec![(String::from("GIT_DIR"), value.to_string())]};
+        assert_eq!(query(&socket, &git_dir("a")).map(|output| output.stdout), Some(String::from("a\n")));
+        assert_eq!(query(&socket, &git_dir("b")).map(|output| output.stdout), Some(String::from("b\n")));
+
+        assert_eq!(watch_plan(&request), (worktree.clone(), vec![(worktree.clone(), false)]));
+
+        // a linked worktree depends on its git directory and the common one
+        let git_dir = dir.join("main.git");
+        let linked = git_dir.join("worktrees").join("linked");
+        let linked_worktree = dir.join("linked");
+        fs::create_dir_all(git_dir.join("refs").join("heads")).unwrap();
+        fs::create_dir_all(&linked).unwrap();
+        fs::create_dir_all(linked_worktree.join("src")).unwrap();
+        fs::write(linked.join("commondir"), "../..\n").unwrap();
+        fs::write(linked.join("HEAD"), "ref: refs/heads/linked\n").unwrap();
+        fs::write(linked_worktree.join(".git"), format!("gitdir: {}\n", linked.display())).unwrap();
+
+        let request = Request {cwd: linked_worktree.join("src"), ..request};
+        let common_dir = linked.join("../..");
+
+        assert_eq!(watch_plan(&request), (linked_worktree.clone(), vec![(linked_worktree.clone(), true),
+                                                                        (linked.clone(), false),
+                                                                        (common_dir.clone(), false),
+                                                                        (common_dir.join("refs"), true)]));
+
+        for changed in [linked.join("HEAD"), git_dir.join("refs").join("heads").join("linked")] {
+            let first = query(&socket, &request).expect("daemon not running");
+            assert_eq!(query(&socket, &request), Some(first.clone()));
+
+            fs::write(changed, "0000000000000000000000000000000000000000\n").unwrap();
+            assert!(changes(&socket, &request, &first));
+        }
+
+        // builds in ignored directories keep the cache
+        let repo = dir.join("repo");
+        Repository::init(&repo).unwrap();
+        fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+        fs::create_dir_all(repo.join("target")).unwrap();
+
+        let request = Request {cwd: repo.clone(), ..request};
+        let first = query(&socket, &request).expect("daemon not running");
+
+        fs::write(repo.join("target").join("built"), "").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(query(&socket, &request), Some(first.clone()));
+
+        // until they are no longer ignored
+        fs::write(repo.join(".gitignore"), "").unwrap();
+        assert!(changes(&socket, &request, &first));
+
+        let second = query(&socket, &request).expect("daemon not running");
+        assert_eq!(query(&socket, &request), Some(second.clone()));
+        fs::write(repo.join("target").join("built"), "again").unwrap();
+        assert!(changes(&socket, &request, &second));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut state = State::default();
+        let now = Instant::now();
+
+        for idx in 0..MAX_ROOTS {
+            state.roots.insert(PathBuf::from(format!("/{}", idx)), Root {generation: 0, watched: true, used: now + Duration::from_secs(idx as u64)});
+        }
+
+        if let Some(idle) = now.checked_sub(ROOT_IDLE * 2) {
+            state.roots.insert(PathBuf::from("/idle"), Root {generation: 0, watched: true, used: idle});
+        }
+
+        // the least recently used roots make room for a new one
+        evict(-1, &mut state);
+
+        assert_eq!(state.roots.len(), MAX_ROOTS - 1);
+        assert!(!state.roots.contains_key(Path::new("/idle")) && !state.roots.contains_key(Path::new("/0")));
+        assert!(state.roots.contains_key(Path::new("/1")));
+    }
+}
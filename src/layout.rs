@@ -0,0 +1,228 @@
+// Measures the prompt and fits it into the width of the terminal.
+
+extern crate libc;
+
+use std::cmp::Reverse;
+use std::env;
+use unicode_width::UnicodeWidthChar;
+use ResultSet;
+use Segment;
+use prompt::{Prompt, ColorOverride, Content, Separator};
+use shell::Shell;
+
+// a computed segment, as handed to the renderers
+pub struct ChainItem {
+    pub result: ResultSet,
+    pub colors: ColorOverride,
+    pub priority: i64,
+    // computes the shorter variant of the result on demand, used before
+    // dropping the segment; None after a timeout
+    pub segment: Option<Box<dyn Segment>>,
+}
+
+impl ChainItem {
+    pub fn is_newline(&self) -> bool {
+        self.result.first().is_some_and(|part| part.content == Content::Newline)
+    }
+}
+
+pub const DEFAULT_PRIORITY: i64 = 50;
+
+// segments with the lowest priority are shortened or dropped first
+const PRIORITIES: [(&str, i64); 8] = [
+    ("root",      100),
+    ("cwd",       90),
+    ("exit_code", 80),
+    ("git",       70),
+    ("read_only", 60),
+    ("username",  30),
+    ("hostname",  20),
+    ("time",      10),
+];
+
+pub fn default_priority(name: &str) -> i64 {
+    PRIORITIES.iter()
+        .find(|(segment, _)| *segment == name.to_lowercase())
+        .map_or(DEFAULT_PRIORITY, |(_, priority)| *priority)
+}
+
+pub fn terminal_width() -> Option<usize> {
+    // set by most shells, but not always exported
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|value| value.parse::<usize>().ok()) {
+        if columns > 0 {
+            return Some(columns);
+        }
+    }
+
+    // stdout is captured by the shell, stderr and stdin are still the terminal
+    for fd in [libc::STDERR_FILENO, libc::STDIN_FILENO] {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+
+        if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    return None;
+}
+
+// number of columns the text takes, ignoring escape sequences
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            width += c.width().unwrap_or(0);
+            continue;
+        }
+
+        // CSI sequences end with a letter, others consist of a single character
+        if let Some('[') = chars.next() {
+            for param in chars.by_ref() {
+                if param.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+    return width;
+}
+
+// Shortens or drops the least important segments of every line of the chain
+// until the line fits into max_width columns.
+pub fn fit_chain(chain: &mut Vec<ChainItem>, max_width: usize, prompt: &Prompt) {
+    let mut start = 0;
+
+    while start < chain.len() {
+        let end = chain[start..].iter()
+            .position(ChainItem::is_newline)
+            .map_or(chain.len(), |pos| start + pos);
+
+        start = fit_line(chain, start, end, max_width, prompt) + 1;
+    }
+}
+
+// returns the end of the line, after some segments are gone
+fn fit_line(chain: &mut Vec<ChainItem>, start: usize, end: usize, max_width: usize, prompt: &Prompt) -> usize {
+    let mut end = end;
+
+    while line_width(&chain[start..end], prompt) > max_width {
+        // of equally important segments the rightmost goes first
+        let victim = match (start..end).min_by_key(|&idx| (chain[idx].priority, Reverse(idx))) {
+            Some(idx) => idx,
+            None => break,
+        };
+
+        // nothing left of it is as good as no compact form
+        let compact = chain[victim].segment.take()
+            .and_then(|segment| segment.compute_compact(prompt))
+            .filter(|compact| !compact.is_empty());
+
+        match compact {
+            Some(compact) => {
+                let colors = chain[victim].colors;
+                chain[victim].result = compact.into_iter().map(|part| colors.apply(part)).collect();
+            },
+            None => {
+                chain.remove(victim);
+                end -= 1;
+            }
+        }
+    }
+    return end;
+}
+
+// segments, their separators and the trailing space
+pub fn line_width(line: &[ChainItem], prompt: &Prompt) -> usize {
+    let mut width = 1;
+
+    for item in line {
+        for part in &item.result {
+            // padded with a space on both sides, literal values stand in for shell expansions
+            width += display_width(&part.content.render(Shell::Bare)) + 2;
+
+            width += match part.separator {
+                Some(Separator::Full) => display_width(&prompt.symbols.separator),
+                Some(Separator::Thin(_)) => display_width(&prompt.symbols.separator_thin),
+                None => 0,
+            };
+        }
+        width += display_width(&prompt.symbols.separator);
+    }
+    return width;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use prompt::Part;
+    use themes::get_symbolset_for_name;
+    use themes::default::default_theme;
+    use themes::color::ColorDepth;
+
+    // counts how often its compact form is asked for
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Segment for Counting {
+        fn compute(&self, _prompt: &Prompt) -> ResultSet {
+            Vec::new()
+        }
+
+        fn compute_compact(&self, _prompt: &Prompt) -> Option<ResultSet> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Some(vec![Part::new("counting", Content::text("c"), 1, 2)])
+        }
+    }
+
+    // shortened into nothing
+    struct Vanishing;
+
+    impl Segment for Vanishing {
+        fn compute(&self, _prompt: &Prompt) -> ResultSet {
+            Vec::new()
+        }
+
+        fn compute_compact(&self, _prompt: &Prompt) -> Option<ResultSet> {
+            Some(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("\x1B[38;5;161m\u{E0B2}\x1B7 1 "), 4);
+        assert_eq!(display_width(" \u{65E5}\u{672C} "), 6);
+        assert_eq!(display_width("\u{2026}"), 1);
+
+        assert_eq!(default_priority("CWD"), 90);
+        assert_eq!(default_priority("stdout"), DEFAULT_PRIORITY);
+    }
+
+    #[test]
+    fn test_lazy_compact() {
+        let prompt = Prompt {theme: default_theme(),
+                             symbols: get_symbolset_for_name("patched"),
+                             shell: Shell::Bare,
+                             depth: ColorDepth::Xterm256};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut chain = vec![ChainItem {result: vec![Part::new("counting", Content::text("counting"), 1, 2)],
+                                        colors: ColorOverride::default(),
+                                        priority: DEFAULT_PRIORITY,
+                                        segment: Some(Box::new(Counting(Arc::clone(&calls))))}];
+
+        // fits, so nothing gets shortened
+        fit_chain(&mut chain, 80, &prompt);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        fit_chain(&mut chain, 8, &prompt);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(chain[0].result[0].content, Content::text("c"));
+
+        // an empty compact form drops the segment
+        chain[0].segment = Some(Box::new(Vanishing));
+        fit_chain(&mut chain, 4, &prompt);
+        assert!(chain.is_empty());
+    }
+}
@@ -0,0 +1,40 @@
+#![allow(clippy::needless_return)]
+
+// The prompt engine: segments, themes, the config loader and the renderers.
+// The prompter binary is a thin command line interface on top of it.
+
+#[macro_use]
+extern crate serde_json;
+extern crate whoami;
+extern crate unicode_width;
+extern crate rhai;
+extern crate git2;
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub mod segments;
+pub mod themes;
+pub mod prompt;
+pub mod shell;
+pub mod layout;
+pub mod config;
+pub mod render;
+pub mod daemon;
+
+use prompt::{Prompt, Part};
+
+// the parts of a segment, empty if there is nothing to show
+pub type ResultSet = Vec<Part>;
+pub type ConfigMap = HashMap<String, Value>;
+
+// computed on a thread of its own, see render::compute_chain
+pub trait Segment: Send {
+    fn compute(&self, prompt: &Prompt) -> ResultSet;
+
+    // a shorter variant, tried before the segment is dropped from a prompt too
+    // wide; an empty one drops it right away
+    fn compute_compact(&self, _prompt: &Prompt) -> Option<ResultSet> {
+        return None;
+    }
+}
@@ -1,13 +1,94 @@
 use themes::Colorcode;
 use themes::Theme;
 use themes::Symbols;
+use themes::color::ColorDepth;
+use shell::Shell;
 
-//               data  , FG       , BG        
-type _Fragment = (String, Colorcode, Colorcode);
-type _FragmentChain = Vec<_Fragment>;
+// what a part shows: plain text or a value the shell expands itself
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Content {
+    Text(String),
+    Hostname,
+    Username,
+    // '$' or '#'
+    Root,
+    Time,
+    // ends the current line, see the newline segment
+    Newline,
+}
+
+// drawn between two parts of a segment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separator {
+    // the powerline arrow, in the background color of the part it points away from
+    Full,
+    // a thin line on the background of both parts
+    Thin(Colorcode),
+}
+
+// A colored piece of a segment, e.g. a directory of cwd. Escape sequences are
+// added by the renderers only.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+    // what the part is about, e.g. "git.ahead"
+    pub name: String,
+    pub content: Content,
+    pub fg: Colorcode,
+    pub bg: Colorcode,
+    // in front of the part, ignored for the first part of a segment
+    pub separator: Option<Separator>,
+}
+
+// per-segment colors from the config, replacing the ones of the theme
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ColorOverride {
+    pub fg: Option<Colorcode>,
+    pub bg: Option<Colorcode>,
+    pub separator: Option<Colorcode>,
+}
 
+#[derive(Clone)]
 pub struct Prompt {
     pub theme: Theme,
     pub symbols: Symbols,
-   
+    pub shell: Shell,
+    pub depth: ColorDepth,
+}
+
+impl Content {
+    pub fn text(text: &str) -> Content {
+        Content::Text(text.to_string())
+    }
+
+    // escaped or expanded for the shell
+    pub fn render(&self, shell: Shell) -> String {
+        match self {
+            Content::Text(text) => shell.escape(text),
+            Content::Hostname   => shell.hostname(),
+            Content::Username   => shell.username(),
+            Content::Root       => shell.root(),
+            Content::Time       => shell.time(),
+            Content::Newline    => String::from("\n"),
+        }
+    }
+}
+
+impl Part {
+    pub fn new(name: &str, content: Content, fg: Colorcode, bg: Colorcode) -> Part {
+        Part {name: name.to_string(), content, fg, bg, separator: None}
+    }
+
+    pub fn separated(self, separator: Separator) -> Part {
+        Part {separator: Some(separator), ..self}
+    }
+}
+
+impl ColorOverride {
+    pub fn map<F: Fn(Colorcode) -> Colorcode>(self, f: F) -> ColorOverride {
+        ColorOverride {fg: self.fg.map(&f), bg: self.bg.map(&f), separator: self.separator.map(&f)}
+    }
+
+    pub fn apply(&self, part: Part) -> Part {
+        Part {fg: self.fg.unwrap_or(part.fg), bg: self.bg.unwrap_or(part.bg), ..part}
+    }
 }
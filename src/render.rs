@@ -0,0 +1,572 @@
+use serde_json::{Value, Map};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use ConfigMap;
+use Segment;
+use ResultSet;
+use themes::*;
+use themes::painter;
+use themes::color::{ColorDepth, reduce};
+use prompt::{Prompt, ColorOverride, Part, Content, Separator};
+use shell::Shell;
+use layout::{ChainItem, display_width, line_width, fit_chain};
+use segments::registry::{Registry, Context};
+use config::{get_theme, get_symbols, get_color_depth, get_color_override, get_priority, get_timeout, get_max_width};
+
+// a segment and its settings from the config
+pub struct ChainEntry {
+    pub segment: Box<dyn Segment>,
+    pub name: String,
+    pub colors: ColorOverride,
+    pub priority: i64,
+    pub timeout: Option<Duration>,
+    // shown instead of a segment running into its timeout, hidden without
+    pub placeholder: Option<String>,
+}
+
+impl ChainEntry {
+    pub fn new(segment: Box<dyn Segment>, name: &str, map: &ConfigMap, segment_object: Option<&Map<String, Value>>) -> ChainEntry {
+        let (timeout, placeholder) = get_timeout(map, name, segment_object);
+
+        ChainEntry {segment,
+                    name: name.to_lowercase(),
+                    colors: get_color_override(map, name, segment_object),
+                    priority: get_priority(map, name, segment_object),
+                    timeout,
+                    placeholder}
+    }
+}
+
+pub type SegmentChain = Vec<ChainEntry>;
+
+pub fn create_prompt(map: &ConfigMap, registry: &Registry, context: &Context) -> String {
+    let shell = context.shell;
+    let prompt = build_prompt(map, shell);
+    let segment_chain = get_segment_chain(map, "segments", registry, context);
+
+    let columns = context.columns;
+
+    // compute
+    let mut result_set = compute_chain(segment_chain, &prompt);
+
+    if let Some(max_width) = get_max_width(map, columns) {
+        fit_chain(&mut result_set, max_width, &prompt);
+    }
+
+    let first_line = result_set.iter().position(ChainItem::is_newline).unwrap_or(result_set.len());
+    let first_line_width = line_width(&result_set[..first_line], &prompt);
+
+    let ps1 = render_prompt(result_set, &prompt);
+
+    // bash has no right prompt, so it is drawn in front of the left one
+    if shell == Shell::Bash && map.contains_key("right_segments") {
+        let right = create_right_prompt(map, registry, &Context {shell: Shell::Bare, ..context.clone()});
+
+        // unless it would run into the first line
+        let width = first_line_width + display_width(&right);
+
+        if columns.is_none_or(|columns| width < columns) {
+            return emulate_right_prompt(&right) + ps1.as_str();
+        }
+    }
+    return ps1;
+}
+
+// the prompt of the right_segments, for zsh RPROMPT and fish_right_prompt
+pub fn create_right_prompt(map: &ConfigMap, registry: &Registry, context: &Context) -> String {
+    let prompt = build_prompt(map, context.shell);
+    let segment_chain = get_segment_chain(map, "right_segments", registry, context);
+
+    let mut result_set = compute_chain(segment_chain, &prompt);
+
+    if let Some(max_width) = get_max_width(map, context.columns) {
+        fit_chain(&mut result_set, max_width, &prompt);
+    }
+
+    return render_right_prompt(result_set, &prompt);
+}
+
+pub fn build_prompt(map: &ConfigMap, shell: Shell) -> Prompt {
+    // reduce all colors to what the terminal is able to show
+    let depth = get_color_depth(map);
+    let theme = reduce_theme(get_theme(map), depth);
+
+    let mut symbols = get_symbols(map);
+
+    // without background colors the full separators would just be blobs
+    if depth == ColorDepth::Monochrome {
+        symbols.separator = symbols.separator_thin.clone();
+        symbols.separator_right = symbols.separator_right_thin.clone();
+    }
+
+    return Prompt {theme,
+                   symbols,
+                   shell,
+                   depth};
+}
+
+// the segments listed under name, e.g. "segments" or "right_segments"
+pub fn get_segment_chain(map: &ConfigMap, name: &str, registry: &Registry, context: &Context) -> SegmentChain {
+    let mut segment_chain: SegmentChain = Vec::new();
+
+    for (key, value) in map {
+        if key.to_lowercase() == name {
+            let segment_definition: Vec<Value> = serde_json::from_value(value.clone()).expect("Error parsing JSON.");
+            
+            for item in segment_definition {
+                match item {
+                    Value::String(s) => {
+                        match registry.create(&s, None, map, context) {
+                            Ok(value) => segment_chain.push(ChainEntry::new(value, &s, map, None)),
+                            Err(reason) => eprintln!("Error: {}", reason),
+                        }
+                    },
+                    Value::Object(o) => {
+                        if o.contains_key("type") {
+                            let seg = o["type"].as_str().expect("key named type not found in map.");
+
+                            match registry.create(seg, Some(o.clone()), map, context) {
+                                Ok(value) => segment_chain.push(ChainEntry::new(value, seg, map, Some(&o))),
+                                Err(reason) => eprintln!("Error: {}", reason),
+                                }
+                        }
+                    },
+                    _ => eprintln!("Not usable"),
+                }
+            }
+        }
+    }
+    return segment_chain;
+}
+
+// Every segment is computed on a thread of its own, the results are collected
+// in the order of the chain. A segment missing its timeout, if it has one, is
+// replaced by its placeholder or left out, its thread is abandoned.
+// The fg and bg overrides apply to every part, the separator override is left
+// to the renderers.
+pub fn compute_chain(segment_chain: SegmentChain, prompt: &Prompt) -> Vec<ChainItem> {
+    let start = Instant::now();
+    let shared = Arc::new(prompt.clone());
+
+    let mut pending = Vec::new();
+
+    for entry in segment_chain {
+        let (sender, receiver) = mpsc::channel();
+        let segment = entry.segment;
+        let prompt = Arc::clone(&shared);
+
+        thread::spawn(move || {
+            let result = segment.compute(&prompt);
+
+            // the segment comes back for its compact form, see fit_chain;
+            // the receiver is gone after a timeout
+            let _ = sender.send((result, Some(segment)));
+        });
+
+        pending.push((receiver, entry.name, entry.colors, entry.priority, entry.timeout, entry.placeholder));
+    }
+
+    let mut result_set: Vec<ChainItem> = Vec::new();
+
+    for (receiver, name, colors, priority, timeout, placeholder) in pending {
+        let colors = colors.map(|code| reduce(code, prompt.depth));
+
+        let apply_colors = |parts: ResultSet| -> ResultSet {
+            parts.into_iter().map(|part| colors.apply(part)).collect()
+        };
+
+        let computed = match timeout {
+            Some(timeout) => receiver.recv_timeout((start + timeout).saturating_duration_since(Instant::now())).ok(),
+            None => receiver.recv().ok(),
+        };
+
+        let (result, segment) = match computed {
+            Some(computed) => computed,
+            None => {
+                let placeholder = placeholder.map(|text| {
+                    Part::new(&format!("{}.timeout", name), Content::text(&text), prompt.theme[PATH_FG], prompt.theme[PATH_BG])
+                });
+                (placeholder.into_iter().collect(), None)
+            }
+        };
+
+        let result = apply_colors(result);
+
+        if ! result.is_empty() { 
+            result_set.push(ChainItem {result, colors, priority, segment});
+        }
+    }
+    return result_set;
+}
+
+fn render_part(part: &Part, shell: Shell) -> String {
+    format!("{}{} {} ", painter::fgcolor(shell, part.fg), painter::bgcolor(shell, part.bg), part.content.render(shell))
+}
+
+// the separator in front of a part, taking the color of the part it points
+// away from: the previous one on the left, the following one on the right
+fn render_part_separator(previous: &Part, part: &Part, separator_fg: Option<Colorcode>, prompt: &Prompt, right: bool) -> String {
+    let shell = prompt.shell;
+
+    match part.separator {
+        Some(Separator::Full) if right => format!("{}{}{}", painter::fgcolor(shell, separator_fg.unwrap_or(part.bg)),
+                                                  painter::bgcolor(shell, previous.bg), prompt.symbols.separator_right),
+        Some(Separator::Full) => format!("{}{}{}", painter::fgcolor(shell, separator_fg.unwrap_or(previous.bg)),
+                                         painter::bgcolor(shell, part.bg), prompt.symbols.separator),
+        Some(Separator::Thin(code)) => {
+            let symbol = if right { &prompt.symbols.separator_right_thin } else { &prompt.symbols.separator_thin };
+            format!("{}{}", painter::fgcolor(shell, separator_fg.unwrap_or(code)), symbol)
+        },
+        None => String::new(),
+    }
+}
+
+pub fn render_prompt(segment_chain: Vec<ChainItem>, prompt: &Prompt) -> String {
+    let mut ps1: String = String::from("");
+
+    let shell = prompt.shell;
+    let length = segment_chain.len();
+
+    // monochrome output contains no escape sequences at all
+    let reset = if prompt.depth == ColorDepth::Monochrome { String::new() } else { painter::reset(shell) };
+
+    ps1.push_str(&shell.start());
+
+    for (idx, item) in segment_chain.iter().enumerate() {
+        // the run before a newline is already closed, the next one starts fresh
+        if item.is_newline() {
+            ps1.push('\n');
+            continue;
+        }
+
+        for (part_idx, part) in item.result.iter().enumerate() {
+            if part_idx > 0 {
+                ps1.push_str(&render_part_separator(&item.result[part_idx - 1], part, item.colors.separator, prompt, false));
+            }
+            ps1.push_str(&render_part(part, shell));
+        }
+
+        // the following segment on the same line
+        let next = segment_chain.get(idx + 1).filter(|next| !next.is_newline());
+
+        if let Some(next) = next {
+            ps1.push_str(&painter::bgcolor(shell, next.result[0].bg));  
+        } else {
+            ps1.push_str(&reset);
+        }
+
+        let last_bg = item.result[item.result.len() - 1].bg;
+
+        ps1.push_str(&painter::fgcolor(shell, item.colors.separator.unwrap_or(last_bg)));
+        ps1.push_str(&prompt.symbols.separator);
+
+        if next.is_none() && (idx + 1) != length {
+            ps1.push_str(&reset);
+        }
+    }
+
+    ps1.push_str(&reset);
+    ps1.push(' ');
+
+    return ps1;
+}
+
+// Mirrors render_prompt: every segment starts with a separator pointing to the
+// left, painted on the background the previous segment ended with.
+pub fn render_right_prompt(segment_chain: Vec<ChainItem>, prompt: &Prompt) -> String {
+    let mut rps1: String = String::from("");
+
+    if segment_chain.is_empty() {
+        return rps1;
+    }
+
+    let shell = prompt.shell;
+    let reset = if prompt.depth == ColorDepth::Monochrome { String::new() } else { painter::reset(shell) };
+
+    // background left of the next separator, the terminal default at first
+    let mut upstream: Option<Colorcode> = None;
+
+    for item in segment_chain.iter() {
+        // the right prompt is a single line
+        if item.is_newline() {
+            continue;
+        }
+
+        if let Some(code) = upstream {
+            rps1.push_str(&painter::bgcolor(shell, code));
+        }
+
+        rps1.push_str(&painter::fgcolor(shell, item.colors.separator.unwrap_or(item.result[0].bg)));
+        rps1.push_str(&prompt.symbols.separator_right);
+
+        for (part_idx, part) in item.result.iter().enumerate() {
+            if part_idx > 0 {
+                rps1.push_str(&render_part_separator(&item.result[part_idx - 1], part, item.colors.separator, prompt, true));
+            }
+            rps1.push_str(&render_part(part, shell));
+        }
+
+        upstream = Some(item.result[item.result.len() - 1].bg);
+    }
+
+    rps1.push_str(&reset);
+
+    return rps1;
+}
+
+// Draws a right prompt rendered for the bare dialect at the end of the line:
+// the cursor is saved, moved to the right edge and restored afterwards, all
+// hidden from bash inside \[ \].
+fn emulate_right_prompt(right: &str) -> String {
+    if right.is_empty() {
+        return String::new();
+    }
+
+    // stops one column short of the edge, printing into the last column would wrap
+    return format!("\\[\\e7\\e[999C\\e[{}D{}\\e8\\]", display_width(right), Shell::Bash.escape(right));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use config::DEFAULT_CONFIG;
+    use themes::default::default_theme;
+
+    // parses a config, pinning the color depth to keep the tests independent of $TERM
+    fn test_config(data: &str) -> ConfigMap {
+        let mut map: ConfigMap = serde_json::from_str(data).expect("Error parsing JSON.");
+        map.entry(String::from("color_depth")).or_insert(Value::from("256"));
+        map
+    }
+
+    #[test]
+    fn test_json_parsing() {
+        // from json.org: "A string is a sequence of zero or more Unicode characters, wrapped in double quotes"
+        let data = r##"{ "segments": [ "hostname" ] }"##;
+        println!("Input: {}", data);
+        let map: ConfigMap = test_config(data);        
+
+        assert_eq!(create_prompt(&map, &Registry::default(), &Context::new(0, &[], Shell::Bash)), r#"\[\e[38;5;250m\]\[\e[48;5;238m\] \h \[\e[0m\]\[\e[38;5;238m\]\[\e[0m\] "#)
+
+    }
+
+    #[test]
+    fn test_zsh_prompt() {
+        let data = r##"{ "segments": [ "username", "root" ] }"##;
+        let map: ConfigMap = test_config(data);
+
+        let ps1 = create_prompt(&map, &Registry::default(), &Context::new(0, &[], Shell::Zsh));
+
+        assert!(ps1.starts_with("%{\x1B[38;5;250m%}"));
+        assert!(ps1.contains(" %n "));
+        assert!(ps1.contains(" %# "));
+        assert!(!ps1.contains("\\["));
+    }
+
+    #[test]
+    fn test_fish_prompt() {
+        let data = r##"{ "segments": [ "username", "exit_code" ] }"##;
+        let map: ConfigMap = test_config(data);
+
+        let ps1 = create_prompt(&map, &Registry::default(), &Context::new(1, &[0, 1, 0], Shell::Fish));
+
+        assert!(ps1.starts_with("\x1B[38;5;250m"));
+        assert!(ps1.contains(&format!(" {} ", whoami::username())));
+        assert!(ps1.contains(" 0|1|0 "));
+        assert!(!ps1.contains("\\u"));
+    }
+
+    #[test]
+    fn test_escaped_cwd() {
+        let map: ConfigMap = test_config(r##"{ "segments": [ "cwd" ], "cwd": { "mode": "plain" } }"##);
+        let context = |shell: Shell| Context {pwd: PathBuf::from("/tmp/inj/$(echo PWNED)"), ..Context::new(0, &[], shell)};
+
+        assert!(create_prompt(&map, &Registry::default(), &context(Shell::Bash)).contains(r" tmp/inj/\\$(echo PWNED) "));
+        assert!(create_prompt(&map, &Registry::default(), &context(Shell::Zsh)).contains(r" tmp/inj/\$(echo PWNED) "));
+    }
+
+    #[test]
+    fn test_symbol_configuration() {
+        let data = r##"{ "segments": [ "hostname" ], "mode": "compatible", "symbols": { "separator": ">" } }"##;
+        let map: ConfigMap = test_config(data);
+
+        let symbols = get_symbols(&map);
+
+        assert_eq!(symbols.separator, ">");
+        assert_eq!(symbols.separator_thin, "\u{276F}");
+        assert_eq!(symbols.lock, "RO");
+        assert!(create_prompt(&map, &Registry::default(), &Context::new(0, &[], Shell::Bash)).contains("\\[\\e[38;5;238m\\]>"));
+    }
+
+    #[test]
+    fn test_color_override() {
+        let data = r##"{ "segments": [ "hostname", { "type": "stdout", "command": ["echo", "hi"], "fg_color": 22, "bg_color": 161 }, "cwd" ],
+                         "hostname": { "fg_color": 1, "separator_color": 3 },
+                         "cwd": { "bg_color": 2 } }"##;
+        let map: ConfigMap = test_config(data);
+
+        let ps1 = create_prompt(&map, &Registry::default(), &Context::new(0, &[], Shell::Bash));
+
+        assert!(ps1.starts_with("\\[\\e[38;5;1m\\]\\[\\e[48;5;238m\\] \\h \\[\\e[48;5;161m\\]\\[\\e[38;5;3m\\]"));
+        assert!(ps1.contains("\\[\\e[38;5;22m\\]\\[\\e[48;5;161m\\] hi "));
+
+        // every part of cwd gets the background
+        assert!(ps1.contains("\\[\\e[48;5;2m\\]"));
+        assert!(!ps1.contains("\\[\\e[48;5;237m\\]"));
+        assert!(!ps1.contains("\\[\\e[48;5;31m\\]"));
+    }
+
+    #[test]
+    fn test_reduced_colors() {
+        let ansi = test_config(r##"{ "segments": [ "hostname" ], "color_depth": "16" }"##);
+        assert_eq!(create_prompt(&ansi, &Registry::default(), &Context::new(0, &[], Shell::Bash)), "\\[\\e[37m\\]\\[\\e[100m\\] \\h \\[\\e[0m\\]\\[\\e[90m\\]\u{E0B0}\\[\\e[0m\\] ");
+
+        let mono = test_config(r##"{ "segments": [ "hostname", "root" ], "color_depth": "none" }"##);
+        assert_eq!(create_prompt(&mono, &Registry::default(), &Context::new(0, &[], Shell::Bash)), " \\h \u{E0B1} \\$ \u{E0B1} ");
+    }
+
+    #[test]
+    fn test_newline_segment() {
+        let data = r##"{ "segments": [ "hostname", "newline", "root" ] }"##;
+        let map: ConfigMap = test_config(data);
+
+        assert_eq!(create_prompt(&map, &Registry::default(), &Context::new(0, &[], Shell::Bash)),
+                   "\\[\\e[38;5;250m\\]\\[\\e[48;5;238m\\] \\h \\[\\e[0m\\]\\[\\e[38;5;238m\\]\u{E0B0}\\[\\e[0m\\]\n\
+                    \\[\\e[38;5;15m\\]\\[\\e[48;5;236m\\] \\$ \\[\\e[0m\\]\\[\\e[38;5;236m\\]\u{E0B0}\\[\\e[0m\\] ");
+    }
+
+    #[test]
+    fn test_right_prompt() {
+        let data = r##"{ "segments": [ "hostname" ],
+                         "right_segments": [ "exit_code", { "type": "stdout", "command": ["echo", "hi"] } ] }"##;
+        let map: ConfigMap = test_config(data);
+
+        assert_eq!(create_right_prompt(&map, &Registry::default(), &Context::new(1, &[], Shell::Zsh)),
+                   "%{\x1B[38;5;161m%}\u{E0B2}%{\x1B[38;5;15m%}%{\x1B[48;5;161m%} 1 \
+                    %{\x1B[48;5;161m%}%{\x1B[38;5;237m%}\u{E0B2}%{\x1B[38;5;250m%}%{\x1B[48;5;237m%} hi %{\x1B[0m%}");
+
+        // bash gets the bare right prompt in front of the left one
+        let ps1 = create_prompt(&map, &Registry::default(), &Context::new(1, &[], Shell::Bash));
+
+        assert!(ps1.starts_with("\\[\\e7\\e[999C\\e[9D\x1B[38;5;161m\u{E0B2}"));
+        assert!(ps1.contains(" hi \x1B[0m\\e8\\]\\[\\e[38;5;250m\\]"));
+        assert!(ps1.ends_with(" \\h \\[\\e[0m\\]\\[\\e[38;5;238m\\]\u{E0B0}\\[\\e[0m\\] "));
+    }
+
+    #[test]
+    fn test_segment_timeout() {
+        let data = r##"{ "timeout": 200,
+                         "segments": [ { "type": "stdout", "command": ["sh", "-c", "sleep 3; echo late"], "timeout_placeholder": "?" },
+                                       { "type": "stdout", "command": ["echo", "first"] },
+                                       { "type": "stdout", "command": ["sh", "-c", "sleep 3; echo hidden"] },
+                                       { "type": "stdout", "command": ["echo", "second"], "timeout": 2000 } ] }"##;
+        let map: ConfigMap = test_config(data);
+
+        let start = Instant::now();
+        let ps1 = create_prompt(&map, &Registry::default(), &Context::new(0, &[], Shell::Bare));
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(ps1.contains(" ? ") && !ps1.contains("late") && !ps1.contains("hidden"));
+
+        // in the order of the config
+        let (placeholder, first, second) = (ps1.find(" ? "), ps1.find("first"), ps1.find("second"));
+        assert!(placeholder < first && first < second);
+
+        assert_eq!(get_timeout(&map, "git", None), (Some(Duration::from_millis(200)), None));
+        assert_eq!(get_timeout(&test_config("{}"), "git", None), (None, None));
+    }
+
+    #[test]
+    fn test_fit_to_width() {
+        let golden = Context {pwd: PathBuf::from("/golden/path"), ..Context::default()};
+
+        let data = r##"{ "segments": [ { "type": "stdout", "command": ["echo", "aaaa"], "priority": 5 }, "cwd",
+                                       { "type": "stdout", "command": ["echo", "bb"] } ] }"##;
+        let map: ConfigMap = test_config(data);
+        let prompt = build_prompt(&map, Shell::Bare);
+
+        let fitted = |max_width: usize| -> String {
+            let mut chain = compute_chain(get_segment_chain(&map, "segments", &Registry::default(), &golden), &prompt);
+            fit_chain(&mut chain, max_width, &prompt);
+            render_prompt(chain, &prompt)
+        };
+
+        // " aaaa " > " golden > path " > " bb " >, plus the trailing space
+        assert!(fitted(29).contains(" aaaa "));
+
+        let ps1 = fitted(20);
+        assert!(!ps1.contains("aaaa") && !ps1.contains("bb"));
+        assert!(ps1.contains(" golden "));
+
+        // cwd gets shortened to the current directory before it is dropped
+        let ps1 = fitted(10);
+        assert!(ps1.contains(" path ") && !ps1.contains("golden"));
+
+        // the width comes with the context, not from the terminal running the tests
+        let context = Context {columns: Some(10), ..golden};
+        assert!(!create_prompt(&map, &Registry::default(), &context).contains("golden"));
+
+        assert_eq!(get_max_width(&test_config(r##"{ "max_width": 0.5 }"##), Some(81)), Some(40));
+        assert_eq!(get_max_width(&map, None), None);
+    }
+
+    // DEFAULT_CONFIG rendered outside of any git repository, with a fixed $PWD
+    fn render_default_config(shell: Shell) -> String {
+        let context = Context {cwd: env::temp_dir(), pwd: PathBuf::from("/golden/path"), ..Context::new(0, &[], shell)};
+
+        let map: ConfigMap = test_config(DEFAULT_CONFIG);
+        create_prompt(&map, &Registry::default(), &context)
+    }
+
+    // fills in the user dependent parts of a golden prompt
+    fn golden(template: &str) -> String {
+        let username_bg = if whoami::username() == "root" { USERNAME_ROOT_BG } else { USERNAME_BG };
+
+        template.replace("{UBG}", &default_theme()[username_bg].to_string())
+                .replace("{USER}", &whoami::username())
+                .replace("{HOST}", &Shell::Fish.hostname())
+                .replace("{PRIV}", &Shell::Fish.root())
+    }
+
+    #[test]
+    fn test_golden_default_config() {
+        let bash = "\\[\\e[38;5;250m\\]\\[\\e[48;5;{UBG}m\\] \\u \\[\\e[48;5;238m\\]\\[\\e[38;5;{UBG}m\\]\u{E0B0}\
+                    \\[\\e[38;5;250m\\]\\[\\e[48;5;238m\\] \\h \\[\\e[48;5;237m\\]\\[\\e[38;5;238m\\]\u{E0B0}\
+                    \\[\\e[38;5;250m\\]\\[\\e[48;5;237m\\] golden \\[\\e[38;5;244m\\]\u{E0B1}\
+                    \\[\\e[38;5;254m\\]\\[\\e[48;5;237m\\] path \\[\\e[48;5;236m\\]\\[\\e[38;5;237m\\]\u{E0B0}\
+                    \\[\\e[38;5;15m\\]\\[\\e[48;5;236m\\] \\$ \\[\\e[0m\\]\\[\\e[38;5;236m\\]\u{E0B0}\\[\\e[0m\\] ";
+
+        let zsh = "%{\x1B[38;5;250m%}%{\x1B[48;5;{UBG}m%} %n %{\x1B[48;5;238m%}%{\x1B[38;5;{UBG}m%}\u{E0B0}\
+                   %{\x1B[38;5;250m%}%{\x1B[48;5;238m%} %m %{\x1B[48;5;237m%}%{\x1B[38;5;238m%}\u{E0B0}\
+                   %{\x1B[38;5;250m%}%{\x1B[48;5;237m%} golden %{\x1B[38;5;244m%}\u{E0B1}\
+                   %{\x1B[38;5;254m%}%{\x1B[48;5;237m%} path %{\x1B[48;5;236m%}%{\x1B[38;5;237m%}\u{E0B0}\
+                   %{\x1B[38;5;15m%}%{\x1B[48;5;236m%} %# %{\x1B[0m%}%{\x1B[38;5;236m%}\u{E0B0}%{\x1B[0m%} ";
+
+        let fish = "\x1B[38;5;250m\x1B[48;5;{UBG}m {USER} \x1B[48;5;238m\x1B[38;5;{UBG}m\u{E0B0}\
+                    \x1B[38;5;250m\x1B[48;5;238m {HOST} \x1B[48;5;237m\x1B[38;5;238m\u{E0B0}\
+                    \x1B[38;5;250m\x1B[48;5;237m golden \x1B[38;5;244m\u{E0B1}\
+                    \x1B[38;5;254m\x1B[48;5;237m path \x1B[48;5;236m\x1B[38;5;237m\u{E0B0}\
+                    \x1B[38;5;15m\x1B[48;5;236m {PRIV} \x1B[0m\x1B[38;5;236m\u{E0B0}\x1B[0m ";
+
+        // tcsh shares the %{ %} wrappers and the %n, %m and %# escapes with zsh
+        let tcsh = zsh;
+
+        let ksh = "\x01\r\x01\x1B[38;5;250m\x01\x01\x1B[48;5;{UBG}m\x01 {USER} \x01\x1B[48;5;238m\x01\x01\x1B[38;5;{UBG}m\x01\u{E0B0}\
+                   \x01\x1B[38;5;250m\x01\x01\x1B[48;5;238m\x01 {HOST} \x01\x1B[48;5;237m\x01\x01\x1B[38;5;238m\x01\u{E0B0}\
+                   \x01\x1B[38;5;250m\x01\x01\x1B[48;5;237m\x01 golden \x01\x1B[38;5;244m\x01\u{E0B1}\
+                   \x01\x1B[38;5;254m\x01\x01\x1B[48;5;237m\x01 path \x01\x1B[48;5;236m\x01\x01\x1B[38;5;237m\x01\u{E0B0}\
+                   \x01\x1B[38;5;15m\x01\x01\x1B[48;5;236m\x01 {PRIV} \x01\x1B[0m\x01\x01\x1B[38;5;236m\x01\u{E0B0}\x01\x1B[0m\x01 ";
+
+        assert_eq!(render_default_config(Shell::Bash), golden(bash));
+        assert_eq!(render_default_config(Shell::Zsh), golden(zsh));
+        assert_eq!(render_default_config(Shell::Fish), golden(fish));
+        assert_eq!(render_default_config(Shell::Tcsh), golden(tcsh));
+        assert_eq!(render_default_config(Shell::Ksh), golden(ksh));
+
+        // fish needs no zero-width markers either, so bare output is identical
+        assert_eq!(render_default_config(Shell::Bare), golden(fish));
+    }
+}
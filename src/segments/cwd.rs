@@ -3,13 +3,18 @@ use Segment;
 use ResultSet;
 use ConfigMap;
 use std::env;
+use std::path::PathBuf;
 use themes::*;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content, Separator};
+use segments::registry::{Registry, SegmentInfo};
+use config::get_cwd_config;
 
 #[derive(Debug)]
 pub struct CwdSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>,
-    pub global_config: Option<ConfigMap>
+    pub global_config: Option<ConfigMap>,
+    pub pwd: PathBuf,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -44,16 +49,11 @@ impl CwdSegment {
    
     fn get_mode(&self) -> Mode {
         if let Some(ref gc) = self.global_config {
-            if let Some(val) = gc.get("mode") {
-                match val {
-                    Value::String(s) => {
-                        match s.to_lowercase().as_str() {
-                            "plain"   => { return Mode::Plain; },
-                            "dironly" => { return Mode::DirOnly;},
-                            _         => { return Mode::Expand;},                            
-                        }
-                    },
-                    _ => {}
+            if let Some(Value::String(s)) = gc.get("mode") {
+                match s.to_lowercase().as_str() {
+                    "plain"   => { return Mode::Plain; },
+                    "dironly" => { return Mode::DirOnly;},
+                    _         => { return Mode::Expand;},                            
                 }
             }
         }
@@ -133,21 +133,7 @@ impl CwdSegment {
     
     fn get_cwd(&self) -> Vec<String> {
 
-        let pwd;
-        
-        if let Ok(env_value) = env::var("PWD") {
-            pwd = env_value;
-        } else {
-            match env::current_dir() {
-                Err(err) => {
-                    eprintln!("Could not get current directory: {}", err);
-                    return Vec::new(); 
-                },
-                Ok(p) => {
-                    pwd = p.display().to_string();
-                }
-            }
-        }
+        let pwd = self.pwd.display().to_string();
 
         let mut can_path: String = self.replace_homedir(&pwd);
 
@@ -157,18 +143,15 @@ impl CwdSegment {
 
         return can_path.split("/").map(|s| s.to_string()).collect();
     }
-}
 
-impl Segment for CwdSegment {
-    fn compute(&self, prompt: &Prompt) -> ResultSet {
+    fn compute_with_mode(&self, prompt: &Prompt, mode: Mode) -> ResultSet {
 
         let home_special_display = prompt.theme[HOME_SPECIAL_DISPLAY] == 1;
 
         let mut names = self.get_cwd();
         
-        let mut cwd_result_string = String::new();
+        let mut parts: ResultSet = Vec::new();
         let full_cwd = self.get_full_cwd();
-        let mode = self.get_mode();
         let max_depth = self.get_max_depth();
         let max_dir_size = self.get_max_dir_size();
 
@@ -188,8 +171,7 @@ impl Segment for CwdSegment {
         if mode == Mode::DirOnly {
             names = names[names.len()-1 ..].to_vec();
         } else if mode == Mode::Plain {
-            cwd_result_string = names.join("/");
-            return (format!(" {} ", cwd_result_string), prompt.theme[CWD_FG], prompt.theme[PATH_BG], String::new(), prompt.theme[RESET]);
+            return vec![Part::new("cwd", Content::Text(names.join("/")), prompt.theme[CWD_FG], prompt.theme[PATH_BG])];
         }
 
         let last_idx = names.len() -1;
@@ -207,32 +189,47 @@ impl Segment for CwdSegment {
             let mut shorty = name.to_string();
 
             if ! ( is_last_dir && full_cwd ) {
-                shorty = self.maybe_shorten_name(&name, max_dir_size);
+                shorty = self.maybe_shorten_name(name, max_dir_size);
             } 
 
-            if idx != 0 {
+            let name = if homedir_included { "cwd.home" } else if is_last_dir { "cwd.current" } else { "cwd.path" };
+            let mut part = Part::new(name, Content::Text(shorty), fg, bg);
 
-                if idx == 1 && homedir_found {
-                    cwd_result_string.push_str(&painter::bgcolor(prompt.theme[PATH_BG]));            
-                    cwd_result_string.push_str(&painter::fgcolor(prompt.theme[HOME_BG]));
-                    cwd_result_string.push_str(&format!("{}", prompt.symbols.separator));
-                } else {
-                    cwd_result_string.push_str(&painter::fgcolor(prompt.theme[SEPARATOR_FG]));
-                    cwd_result_string.push_str(&format!("{}", prompt.symbols.separator_thin));
-                }
+            if idx == 1 && homedir_found {
+                part = part.separated(Separator::Full);
+            } else if idx != 0 {
+                part = part.separated(Separator::Thin(prompt.theme[SEPARATOR_FG]));
             }
-            
-            cwd_result_string.push_str(&painter::bgcolor(bg));            
-            cwd_result_string.push_str(&painter::fgcolor(fg));
-            
-            cwd_result_string.push_str(&format!(" {} ", &shorty));
+            parts.push(part);
         }
+        return parts;
+    }
+}
 
-        let downstream = if homedir_found && names.len() == 1 {prompt.theme[HOME_BG] } else {prompt.theme[PATH_BG]};
-        
-        let fg = if homedir_found {prompt.theme[HOME_FG] } else {prompt.theme[CWD_FG]};
-        let bg = if homedir_found {prompt.theme[HOME_BG] } else {prompt.theme[PATH_BG]};
-        
-        return (format!("{}", cwd_result_string), fg, bg, String::new(), downstream);
+impl Segment for CwdSegment {
+    fn compute(&self, prompt: &Prompt) -> ResultSet {
+        return self.compute_with_mode(prompt, self.get_mode());
+    }
+
+    // only the current directory
+    fn compute_compact(&self, prompt: &Prompt) -> Option<ResultSet> {
+        if self.get_mode() == Mode::DirOnly {
+            return None;
+        }
+        return Some(self.compute_with_mode(prompt, Mode::DirOnly));
     }
 }
+
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "cwd",
+                                   description: "Current working directory, configured in the \"cwd\" block",
+                                   options: &[
+                                  ("mode", "\"plain\" shows the path as text, \"dironly\" the current directory only"),
+                                  ("max_depth", "maximum number of directories shown, default 5"),
+                                  ("max_dir_size", "maximum number of characters of each directory"),
+                                  ("full_cwd", "if true, max_dir_size does not shorten the current directory"),
+                              ]},
+                      |options, config, context| Box::new(CwdSegment {options,
+                                                                      global_config: get_cwd_config(config, "cwd"),
+                                                                      pwd: context.pwd.clone()}));
+}
@@ -2,7 +2,7 @@ use serde_json::{Value, Map};
 use Segment;
 use themes::*;
 use ResultSet;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
 
 #[derive(Debug)]
 pub struct _DummySegment {
@@ -12,7 +12,7 @@ pub struct _DummySegment {
 
 impl Segment for _DummySegment {
     fn compute(&self, prompt: &Prompt) -> ResultSet {
-        return (self.name.clone(), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
+        return vec![Part::new("dummy", Content::text(&self.name), prompt.theme[RESET], prompt.theme[RESET])];
     }
 }
 
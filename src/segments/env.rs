@@ -3,7 +3,8 @@ use Segment;
 use ResultSet;
 use std::env;
 use themes::*;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct EnvSegment{
@@ -20,18 +21,24 @@ impl Segment for EnvSegment {
             Some(val) => {
                 match val["var"]{
                     serde_json::Value::String(ref value) => {
-                        if let Ok(env_value) = env::var(&value) {
-                            (format!(" {} ", env_value), fg, bg, String::new(), prompt.theme[RESET])
+                        if let Ok(env_value) = env::var(value) {
+                            vec![Part::new("env", Content::text(&env_value), fg, bg)]
                         } else {
-                            (String::from(" ENV:<not_found> "), fg, bg, String::new(), prompt.theme[RESET])
+                            vec![Part::new("env", Content::text("ENV:<not_found>"), fg, bg)]
                         }
                         
                         },
-                    _ => (String::from("Variable name not found"), fg, bg, String::new(), prompt.theme[RESET]),
+                    _ => vec![Part::new("env", Content::text("Variable name not found"), fg, bg)],
                     }
             },
-            None => (String::from("No option given"), fg, bg, String::new(), prompt.theme[RESET])
+            None => vec![Part::new("env", Content::text("No option given"), fg, bg)]
         }
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "env",
+                                   description: "Value of an environment variable",
+                                   options: &[("var", "name of the environment variable")]},
+                      |options, _, _| Box::new(EnvSegment {options}));
+}
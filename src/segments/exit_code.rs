@@ -2,22 +2,37 @@ use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
 use themes::*;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct ExitCodeSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>,
-    pub prev_error:i32
+    pub prev_error:i32,
+    pub pipestatus: Vec<i32>,
 }
 
 impl Segment for ExitCodeSegment {
     fn compute(&self, prompt: &Prompt) -> ResultSet {
-        if self.prev_error == 0 {
-            return (String::from(""), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
+        let pipeline_failed = self.pipestatus.len() > 1 && self.pipestatus.iter().any(|&s| s != 0);
+
+        if pipeline_failed {
+            // show every status of the pipeline, e.g. " 0|1|0 "
+            let codes: Vec<String> = self.pipestatus.iter().map(|s| s.to_string()).collect();
+            return vec![Part::new("exit_code", Content::Text(codes.join("|")), prompt.theme[CMD_FAILED_FG], prompt.theme[CMD_FAILED_BG])];
+        } else if self.prev_error == 0 {
+            return Vec::new();
         } else {
-            return (format!(" {} ", self.prev_error), prompt.theme[CMD_FAILED_FG], prompt.theme[CMD_FAILED_BG], String::new(), prompt.theme[RESET]);
+            return vec![Part::new("exit_code", Content::Text(self.prev_error.to_string()), prompt.theme[CMD_FAILED_FG], prompt.theme[CMD_FAILED_BG])];
         }
         
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "exit_code",
+                                   description: "Exit code of the last command or pipeline, if it failed",
+                                   options: &[]},
+                      |options, _, context| Box::new(ExitCodeSegment {options, prev_error: context.prev_error, pipestatus: context.pipestatus.clone()}));
+}
@@ -1,37 +1,45 @@
-// varius ways to disable unused code warnings are here:
-// https://stackoverflow.com/a/25877389
-#![allow(dead_code)]
-
 use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
-use std::process::Command;
+use ConfigMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use config::get_cwd_config;
+use daemon::{cached_output, is_running, socket_path};
+use segments::git_native::read_status;
 use std::str;
 use themes::*;
-use regex::Regex;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content, Separator};
+use segments::registry::{Registry, SegmentInfo};
 
 pub struct RepoSet {
+    pub name: &'static str,
     pub symbol: &'static str,
     pub fg: usize,
     pub bg: usize,
 }
 
-const DETACHED:RepoSet   = RepoSet{symbol: "\u{2693}",         fg: RESET, bg: RESET};
-
-const AHEAD:RepoSet      = RepoSet{symbol: "\u{2B06}",         fg: GIT_AHEAD_FG, bg: GIT_AHEAD_BG};
-const BEHIND:RepoSet     = RepoSet{symbol: "\u{2B07}",         fg: GIT_BEHIND_FG, bg: GIT_BEHIND_BG};
-const STAGED:RepoSet     = RepoSet{symbol: "\u{2714}",         fg: GIT_STAGED_FG, bg: GIT_STAGED_BG};
-const CHANGED:RepoSet    = RepoSet{symbol: "\u{270E}",         fg: GIT_NOTSTAGED_FG, bg: GIT_NOTSTAGED_BG};
-const NEW:RepoSet        = RepoSet{symbol: "?",                fg: GIT_UNTRACKED_FG, bg: GIT_UNTRACKED_BG};
-const CONFLICTED:RepoSet = RepoSet{symbol: "\u{273C}",         fg: GIT_CONFLICTED_FG, bg: GIT_CONFLICTED_BG};
-
-const STASH:RepoSet      = RepoSet{symbol: "\u{2398}",         fg: RESET, bg: RESET};
-const GIT:RepoSet        = RepoSet{symbol: "\u{E0A0}",         fg: RESET, bg: RESET};
-const HG:RepoSet         = RepoSet{symbol: "\u{263F}",         fg: RESET, bg: RESET};
-const BZR:RepoSet        = RepoSet{symbol: "\u{2B61}\u{20DF}", fg: RESET, bg: RESET};
-const FOSSIL:RepoSet     = RepoSet{symbol: "\u{2332}",         fg: RESET, bg: RESET};
-const SVN:RepoSet        = RepoSet{symbol: "\u{2446}",         fg: RESET, bg: RESET};
+const AHEAD:RepoSet      = RepoSet{name: "ahead", symbol: "\u{2B06}",         fg: GIT_AHEAD_FG, bg: GIT_AHEAD_BG};
+const BEHIND:RepoSet     = RepoSet{name: "behind", symbol: "\u{2B07}",         fg: GIT_BEHIND_FG, bg: GIT_BEHIND_BG};
+const STAGED:RepoSet     = RepoSet{name: "staged", symbol: "\u{2714}",         fg: GIT_STAGED_FG, bg: GIT_STAGED_BG};
+const CHANGED:RepoSet    = RepoSet{name: "changed", symbol: "\u{270E}",         fg: GIT_NOTSTAGED_FG, bg: GIT_NOTSTAGED_BG};
+const NEW:RepoSet        = RepoSet{name: "new", symbol: "?",                fg: GIT_UNTRACKED_FG, bg: GIT_UNTRACKED_BG};
+const CONFLICTED:RepoSet = RepoSet{name: "conflicted", symbol: "\u{273C}",         fg: GIT_CONFLICTED_FG, bg: GIT_CONFLICTED_BG};
+const RENAMED:RepoSet    = RepoSet{name: "renamed", symbol: "\u{279C}",         fg: GIT_RENAMED_FG, bg: GIT_RENAMED_BG};
+const DELETED:RepoSet    = RepoSet{name: "deleted", symbol: "\u{2716}",         fg: GIT_DELETED_FG, bg: GIT_DELETED_BG};
+const SUBMODULE:RepoSet  = RepoSet{name: "submodule", symbol: "\u{29C9}",         fg: GIT_SUBMODULE_FG, bg: GIT_SUBMODULE_BG};
+
+const STASH:RepoSet      = RepoSet{name: "stash", symbol: "\u{2398}",         fg: GIT_STASH_FG, bg: GIT_STASH_BG};
+
+// files in the git directory marking an operation waiting for the user,
+// rebases are detected by their directories
+const OPERATIONS: [(&str, &str); 4] = [
+    ("MERGE_HEAD",       "MERGE"),
+    ("CHERRY_PICK_HEAD", "CHERRY-PICK"),
+    ("REVERT_HEAD",      "REVERT"),
+    ("BISECT_LOG",       "BISECT"),
+];
 
 fn get_fg_bg_for_repo_set(theme: Theme, reposet: &RepoSet) -> (Colorcode, Colorcode) {
     return (theme[reposet.fg], theme[reposet.bg]);
@@ -39,7 +47,10 @@ fn get_fg_bg_for_repo_set(theme: Theme, reposet: &RepoSet) -> (Colorcode, Colorc
 
 #[derive(Debug)]
 pub struct GitSegment {
-    pub options: Option<Map<String, Value>>
+    pub options: Option<Map<String, Value>>,
+    // the "git" and "vcs" blocks of the config
+    pub global_config: Option<ConfigMap>,
+    pub cwd: PathBuf,
 }
 
 pub struct BranchInfo {
@@ -49,42 +60,32 @@ pub struct BranchInfo {
     pub behind: i32,
 }
 
-fn parse_git_branch_info(input: &str) -> Option<BranchInfo> {
-    let re = Regex::new(
-        r###"^## (?P<local>\S+?)(\.{3}(?P<remote>\S+?)( \[(ahead (?P<ahead>\d+)(, )?)?(behind (?P<behind>\d+))?\])?)?$"###
-    ).expect("Problem creating regular expressen. Bail out.");
-    
-    let caps = match re.captures(input) {
-        None => return None,
-        Some(val) => val,
-    };
-
-    let mut local = String::new();
-    let mut remote = String::new();
-    let mut ahead = 0;
-    let mut behind = 0;
-    
-    if let Some(m) = caps.name("local") {
-        local = m.as_str().to_string();
-    }
+// the "# branch.*" headers of porcelain v2, None for a detached HEAD
+fn parse_git_branch_info(lines: &[&str]) -> Option<BranchInfo> {
+    let mut info = BranchInfo {local: String::new(), remote: String::new(), ahead: 0, behind: 0};
 
-    if let Some(m) = caps.name("remote") {
-        remote = m.as_str().to_string();
-    }
+    for line in lines {
+        let mut fields = line.split(' ');
 
-    if let Some(m) = caps.name("ahead") {
-        if let Ok(v) = m.as_str().parse::<i32>() {
-            ahead = v;
+        if fields.next() != Some("#") {
+            continue;
         }
-    }
 
-    if let Some(m) = caps.name("behind") {
-        if let Ok(v) = m.as_str().parse::<i32>() {
-            behind = v;
+        match fields.next() {
+            Some("branch.head") => info.local = fields.next().unwrap_or_default().to_string(),
+            Some("branch.upstream") => info.remote = fields.next().unwrap_or_default().to_string(),
+            Some("branch.ab") => {
+                info.ahead = fields.next().and_then(|ahead| ahead.trim_start_matches('+').parse().ok()).unwrap_or(0);
+                info.behind = fields.next().and_then(|behind| behind.trim_start_matches('-').parse().ok()).unwrap_or(0);
+            },
+            _ => {},
         }
     }
 
-    Some(BranchInfo {local, remote, ahead, behind})
+    if info.local.is_empty() || info.local == "(detached)" {
+        return None;
+    }
+    return Some(info);
 }
 
 
@@ -96,67 +97,83 @@ fn number_or_blank(number: i32) -> String {
     }
 }
 
+#[derive(Default)]
 pub struct RepoStats {
     pub new: i32,
     pub conflicted: i32,
     pub changed: i32,
     pub staged: i32,
+    pub renamed: i32,
+    pub deleted: i32,
+    // submodules with a new commit, changes or untracked files
+    pub submodules: i32,
     
     pub ahead: i32, // these two come from the sibling struct.
     pub behind: i32,
+
+    // stashes are not part of the status, see count_stashes
+    pub stash: i32,
 }
 
 impl RepoStats {
     fn dirty(&self) -> bool {
-        ( self.new + self.conflicted + self.changed + self.staged ) > 0
+        ( self.new + self.conflicted + self.changed + self.staged + self.renamed + self.deleted + self.submodules ) > 0
     }
 
-    fn print(&self) {
-        println!("New        : {}", self.new);
-        println!("Conflicted : {}", self.conflicted);
-        println!("Changed    : {}", self.changed);
-        println!("Staged     : {}", self.staged);
-        println!("Ahead      : {}", self.ahead);
-        println!("Behind     : {}", self.behind);
-    } 
+    // an entry of the status with the XY letters of porcelain v2, '.' for unchanged
+    pub fn count(&mut self, index: char, worktree: char, dirty_submodule: bool) {
+        match index {
+            '.' => {},
+            'R' | 'C' => self.renamed += 1,
+            'D' => self.deleted += 1,
+            _ => self.staged += 1,
+        }
+
+        // the worktree of a submodule is the submodule itself
+        if dirty_submodule {
+            self.submodules += 1;
+            return;
+        }
+
+        match worktree {
+            '.' => {},
+            'D' => self.deleted += 1,
+            _ => self.changed += 1,
+        }
+    }
 }
 
+// the entries of porcelain v2: "1 XY sub ..." for changes, "2 XY sub ..." for
+// renames and copies, "u XY ..." for conflicts and "? path" for untracked files
 fn parse_git_stats(lines: &[&str]) -> RepoStats {
-    let mut new = 0;
-    let mut conflicted = 0;
-    let mut changed = 0;
-    let mut staged = 0;
-    
-    for line in lines {
-        let code = &line[..2];
+    let mut stats = RepoStats::default();
 
-        if code == "??" {
-            new += 1;
-        } else {
-            match code {
-                "DD" | "AU" | "UD" | "UA" | "DU" | "AA" | "UU" => conflicted += 1,
-                _ => {
-                    if code.chars().nth(1).unwrap_or(' ') != ' ' {
-                        changed += 1;
-                    }
-                    if code.chars().nth(0).unwrap_or(' ') != ' '{
-                        staged += 1;
-                    }
-                }
-            }
+    for line in lines {
+        let fields: Vec<&str> = line.splitn(4, ' ').collect();
+
+        match fields[0] {
+            "?" => stats.new += 1,
+            "u" => stats.conflicted += 1,
+            "1" | "2" if fields.len() == 4 => {
+                let mut xy = fields[1].chars();
+                let (index, worktree) = (xy.next().unwrap_or('.'), xy.next().unwrap_or('.'));
+
+                // "S<c><m><u>" for submodules with a new commit, changes or untracked files
+                let sub = fields[2];
+                let dirty_submodule = sub.starts_with('S') && sub[1..].chars().any(|flag| flag != '.');
+
+                stats.count(index, worktree, dirty_submodule);
+            },
+            // headers and ignored files
+            _ => {},
         }
     }
-    RepoStats{new, conflicted, changed, staged, ahead: 0, behind: 0}
+    return stats;
 }
 
 
-fn get_git_detached_branch() -> String {
-    let git_cmd_result = Command::new("git")
-        .env("LANG", "C")
-        .arg("describe")
-        .arg("--tags")
-        .arg("--always")
-        .output();
+fn get_git_detached_branch(dir: &Path) -> String {
+    let git_cmd_result = cached_output(dir, &["git", "describe", "--tags", "--always"], &[("LANG", "C")]);
 
     match git_cmd_result {
         Err(err) => {
@@ -164,8 +181,8 @@ fn get_git_detached_branch() -> String {
             return String::from("<git-err>");
         },
         Ok(output) => {
-            if output.status.success() {
-                return String::from_utf8(output.stdout).unwrap_or("*err*".to_string()).trim_end().to_string();
+            if output.success {
+                return output.stdout.trim_end().to_string();
             } else {
                 return String::from("Big Bang");
             }
@@ -173,89 +190,221 @@ fn get_git_detached_branch() -> String {
     }
 }
 
-fn add_vcs_details(theme: Theme, symbols: Symbols, upstream: Colorcode, repo_stats: RepoStats) -> (String, Colorcode) {
-    let mut result = String::new();
+fn add_vcs_details(prompt: &Prompt, parts: &mut ResultSet, repo_stats: RepoStats, shown: &dyn Fn(&str) -> bool) {
 
     //          **************            
     // before > branch > a > b > follow
 
-    let ( data, cc ) = add_vcs_part(theme, symbols, repo_stats.ahead, &AHEAD, upstream);
-    result.push_str(&data);
+    let details = [(repo_stats.ahead, &AHEAD),
+                   (repo_stats.behind, &BEHIND),
+                   (repo_stats.staged, &STAGED),
+                   (repo_stats.renamed, &RENAMED),
+                   (repo_stats.changed, &CHANGED),
+                   (repo_stats.deleted, &DELETED),
+                   (repo_stats.new, &NEW),
+                   (repo_stats.submodules, &SUBMODULE),
+                   (repo_stats.conflicted, &CONFLICTED),
+                   (repo_stats.stash, &STASH)];
+
+    for (value, repo_set) in details.iter() {
+        if shown(repo_set.name) {
+            add_vcs_part(prompt, parts, *value, repo_set);
+        }
+    }
+}
 
-    let ( data, cc ) = add_vcs_part(theme, symbols, repo_stats.behind, &BEHIND, cc);
-    result.push_str(&data);
+fn add_vcs_part(prompt: &Prompt, parts: &mut ResultSet, value: i32, repo_set: &RepoSet) {
+    if value < 1 { return; }
+    let (fg, bg) = get_fg_bg_for_repo_set(prompt.theme, repo_set);
+    let text = format!("{}{}", number_or_blank(value), repo_set.symbol);
 
-    let ( data, cc ) = add_vcs_part(theme, symbols, repo_stats.staged, &STAGED, cc);
-    result.push_str(&data);
+    parts.push(Part::new(&format!("git.{}", repo_set.name), Content::Text(text), fg, bg).separated(Separator::Full));
+}
 
-    let ( data, cc ) = add_vcs_part(theme, symbols, repo_stats.changed, &CHANGED, cc);
-    result.push_str(&data);
 
-    let ( data, cc ) = add_vcs_part(theme, symbols, repo_stats.new, &NEW, cc);
-    result.push_str(&data);
+// the worktree and its git directory, following the .git file of linked
+// worktrees and submodules
+pub fn find_repository(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    for ancestor in dir.ancestors() {
+        let dot_git = ancestor.join(".git");
 
-    let ( data, cc ) = add_vcs_part(theme, symbols, repo_stats.conflicted, &CONFLICTED, cc);
-    result.push_str(&data);
+        if dot_git.is_dir() {
+            return Some((ancestor.to_path_buf(), dot_git));
+        }
 
-    (result, cc)
+        if dot_git.is_file() {
+            let content = fs::read_to_string(&dot_git).ok()?;
+            return Some((ancestor.to_path_buf(), ancestor.join(content.strip_prefix("gitdir:")?.trim())));
+        }
+    }
+    return None;
+}
+
+fn find_git_dir(dir: &Path) -> Option<PathBuf> {
+    if let Ok(git_dir) = env::var("GIT_DIR") {
+        return Some(PathBuf::from(git_dir));
+    }
+    return find_repository(dir).map(|(_, git_dir)| git_dir);
 }
 
-// if upstream != RESET than paint a separator with this color *before*
-fn add_vcs_part(theme: Theme, symbols: Symbols, value: i32, repo_set: &RepoSet, upstream: Colorcode) -> (String, Colorcode) {
-    if value < 1 { return (String::new(), upstream); }
-    let (fg, bg) = get_fg_bg_for_repo_set(theme, repo_set);
-    // separator
-    let mut ret_val = String::from(format!("{}{}{}", painter::fgcolor(upstream), painter::bgcolor(bg), symbols.separator));
-    // data
-    ret_val.push_str(&format!("{}{} {}{} ", painter::fgcolor(fg), painter::bgcolor(bg), number_or_blank(value), repo_set.symbol));
-    (ret_val, bg)
-            
+// the git directory shared by all worktrees, with the refs and their reflogs
+pub fn find_common_dir(git_dir: &Path) -> PathBuf {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
 }
 
+// the operation in progress with its step, e.g. "REBASE 3/7"
+fn read_operation(git_dir: &Path) -> Option<String> {
+    let read_number = |file: PathBuf| -> Option<u32> {
+        fs::read_to_string(file).ok()?.trim().parse().ok()
+    };
 
-impl Segment for GitSegment {
-    fn compute(&self, prompt: &Prompt) -> ResultSet { 
+    let with_progress = |name: &str, dir: &Path, step: &str, total: &str| -> String {
+        match (read_number(dir.join(step)), read_number(dir.join(total))) {
+            (Some(step), Some(total)) => format!("{} {}/{}", name, step, total),
+            _ => name.to_string(),
+        }
+    };
 
-        // git status --porcelain -b
-       
-        let git_cmd_result = Command::new("git")
-            .env("LANG", "C")
-            .arg("status")
-            .arg("--porcelain")
-            .arg("-b")
-            .output();
-
-       
-        if let Err(err) = git_cmd_result {
-            eprintln!("Failed to execute git: {}", err);
-            return (String::from("<git-err>"), prompt.theme[REPO_DIRTY_FG], prompt.theme[REPO_DIRTY_BG], String::new(), prompt.theme[RESET]);
+    let rebase_merge = git_dir.join("rebase-merge");
+    if rebase_merge.is_dir() {
+        return Some(with_progress("REBASE", &rebase_merge, "msgnum", "end"));
+    }
+
+    // git am, or git rebase with the apply backend
+    let rebase_apply = git_dir.join("rebase-apply");
+    if rebase_apply.is_dir() {
+        let name = if rebase_apply.join("rebasing").exists() {
+            "REBASE"
+        } else if rebase_apply.join("applying").exists() {
+            "AM"
+        } else {
+            "AM/REBASE"
+        };
+        return Some(with_progress(name, &rebase_apply, "next", "last"));
+    }
+
+    return OPERATIONS.iter()
+        .find(|(file, _)| git_dir.join(file).exists())
+        .map(|(_, name)| name.to_string());
+}
+
+// entries of the reflog of refs/stash, which all worktrees share
+fn count_stashes(git_dir: &Path) -> i32 {
+    match fs::read_to_string(find_common_dir(git_dir).join("logs").join("refs").join("stash")) {
+        Ok(log) => log.lines().count() as i32,
+        Err(_) => 0,
+    }
+}
+
+// runs git status --porcelain=v2 --branch, and git describe for a detached HEAD
+fn read_status_from_command(dir: &Path, untracked: bool) -> Result<Option<(String, RepoStats)>, String> {
+    let mut command = vec!["git", "status", "--porcelain=v2", "--branch"];
+    if !untracked {
+        command.push("--untracked-files=no");
+    }
+
+    let output = cached_output(dir, &command, &[("LANG", "C")]).map_err(|err| err.to_string())?;
+
+    let lines:Vec<&str> = output.stdout.lines().collect();
+
+    // no git repo, bail-out
+    if lines.is_empty() {
+        return Ok(None);
+    }
+
+    let branch_name;
+    let branch_info;
+
+    if let Some(bi) = parse_git_branch_info(&lines) {
+        branch_name = bi.local.clone();
+        branch_info = bi;
+    } else {
+        branch_name = get_git_detached_branch(dir);
+        branch_info = BranchInfo {local: String::new(), remote: String::new(), ahead: 0, behind: 0};
+    }
+
+    let mut repo_stats = parse_git_stats(&lines);
+
+    repo_stats.ahead = branch_info.ahead;
+    repo_stats.behind = branch_info.behind;
+
+    return Ok(Some((branch_name, repo_stats)));
+}
+
+// the parts which could be hidden with the "parts" option
+const PART_NAMES: [&str; 11] = ["ahead", "behind", "staged", "renamed", "changed", "deleted",
+                                "new", "submodule", "conflicted", "stash", "operation"];
+
+impl GitSegment {
+    // options of the segment object take precedence over the vcs block
+    fn get_option(&self, key: &str) -> Option<&Value> {
+        if let Some(value) = self.options.as_ref().and_then(|options| options.get(key)) {
+            return Some(value);
         }
+        return self.global_config.as_ref().and_then(|gc| gc.get(key));
+    }
 
-        let output = git_cmd_result.unwrap();
-        let std_out_value = str::from_utf8(&output.stdout).unwrap();
+    fn get_bool(&self, key: &str, default_value: bool) -> bool {
+        match self.get_option(key) {
+            Some(Value::Bool(value)) => *value,
+            _ => default_value,
+        }
+    }
 
-        let lines:Vec<&str> = std_out_value.lines().collect();
+    // "command" runs git, cached by the daemon, "native" reads the repository
+    // itself; by default git only runs if a daemon caches its output
+    fn native(&self) -> bool {
+        match self.get_option("backend") {
+            Some(Value::String(backend)) => backend == "native",
+            _ => !is_running(&socket_path()),
+        }
+    }
 
-        // no git repo, bail-out
-        if lines.len() == 0 {
-            return (String::new(), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
+    // all parts are shown without a "parts" list
+    fn shows(&self, name: &str) -> bool {
+        match self.get_option("parts") {
+            Some(Value::Array(names)) => names.iter().any(|part| part.as_str() == Some(name)),
+            _ => true,
         }
-        
-        let branch_name;
-        let branch_info;
-        
-        if let Some(bi) = parse_git_branch_info(lines[0]) {
-            branch_name = bi.local.clone();
-            branch_info = bi;
-        } else {
-            branch_name = get_git_detached_branch();
-            branch_info = BranchInfo {local: String::new(), remote: String::new(), ahead: 0, behind: 0};
+    }
+
+    fn check_parts(&self) {
+        if let Some(Value::Array(names)) = self.get_option("parts") {
+            for name in names {
+                if !name.as_str().is_some_and(|name| PART_NAMES.contains(&name)) {
+                    eprintln!("Unknown part of the git segment: {}", name);
+                }
+            }
         }
+    }
+}
 
-        let mut repo_stats = parse_git_stats(&lines[1..]);
+impl Segment for GitSegment {
+    fn compute(&self, prompt: &Prompt) -> ResultSet { 
 
-        repo_stats.ahead = branch_info.ahead;
-        repo_stats.behind = branch_info.behind;
+        self.check_parts();
+
+        let untracked = self.get_bool("untracked", true);
+
+        let native = if self.native() { Some(read_status(&self.cwd, untracked)) } else { None };
+
+        let status = match native {
+            Some(Ok(status)) => Ok(status),
+            // e.g. repository extensions unknown to libgit2, the git binary copes with them
+            _ => read_status_from_command(&self.cwd, untracked),
+        };
+
+        let (branch_name, mut repo_stats) = match status {
+            Ok(Some(status)) => status,
+            Ok(None) => return Vec::new(),
+            Err(err) => {
+                eprintln!("Failed to execute git: {}", err);
+                return vec![Part::new("git", Content::text("<git-err>"), prompt.theme[REPO_DIRTY_FG], prompt.theme[REPO_DIRTY_BG])];
+            }
+        };
 
         let fg;
         let bg;
@@ -269,15 +418,28 @@ impl Segment for GitSegment {
             bg = prompt.theme[REPO_CLEAN_BG];        
         }
 
-        let mut git_line: String = format!("{} ", branch_name);
+        let branch_text = if self.get_bool("show_symbol", false) {
+            format!("{} {}", prompt.symbols.vcs, branch_name)
+        } else {
+            branch_name
+        };
 
-        // repo_stats.print();
+        let mut parts: ResultSet = vec![Part::new("git.branch", Content::Text(branch_text), fg, bg)];
 
-        let (data, cc) = add_vcs_details(prompt.theme, prompt.symbols, bg, repo_stats);
-        
-        git_line.push_str(&data);
+        let git_dir = find_git_dir(&self.cwd);
+
+        if let Some(ref git_dir) = git_dir {
+            repo_stats.stash = count_stashes(git_dir);
+        }
+
+        if let Some(operation) = git_dir.as_ref().filter(|_| self.shows("operation")).and_then(|git_dir| read_operation(git_dir)) {
+            parts.push(Part::new("git.operation", Content::Text(operation), prompt.theme[GIT_OPERATION_FG], prompt.theme[GIT_OPERATION_BG])
+                       .separated(Separator::Full));
+        }
+
+        add_vcs_details(prompt, &mut parts, repo_stats, &|name| self.shows(name));
         
-        return (format!(" {}", git_line), fg, bg, String::new(), cc);
+        return parts;
 
 
     }
@@ -285,44 +447,118 @@ impl Segment for GitSegment {
 
 }
 
-/* Python seach strings is in fact a (silent) concatenation of two strings, see single quote two times between #1 an #2 capture group.
-re.search('^## (?P<local>\S+?)''(\.{3}(?P<remote>\S+?)( \[(ahead (?P<ahead>\d+)(, )?)?(behind (?P<behind>\d+))?\])?)?$', text).groupdict()
 
-"## master...origin/master"           -> {'behind': None, 'local': 'master', 'remote': 'origin/master', 'ahead': None}
-"## master...origin/master [ahead 1]" -> {'behind': None, 'local': 'master', 'remote': 'origin/master', 'ahead': '1'}
-*/
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "git",
+                                   description: "Branch and status of the git repository, configured in the \"vcs\" block",
+                                   options: &[("backend", "\"command\" runs git, cached by prompter daemon, \"native\" reads the repository with libgit2; command if a daemon is running by default"),
+                                              ("show_symbol", "start with the symbol of the version control system"),
+                                              ("parts", "the parts to show next to the branch, e.g. [\"ahead\", \"behind\", \"stash\"]"),
+                                              ("untracked", "false to not look for untracked files")]},
+                      |options, config, context| Box::new(GitSegment {options,
+                                                                      global_config: get_cwd_config(config, "git"),
+                                                                      cwd: context.cwd.clone()}));
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use config::{get_priority, get_color_override};
 
-    // cargo test segments::git::test::test_regex_differences -- --nocapture
-    // see: https://crates.io/crates/regex
-    
     #[test]
-    fn test_regex_differences() {
+    fn test_porcelain_v2() {
+        let output = "# branch.oid 1234\n\
+                      # branch.head main\n\
+                      # branch.upstream origin/main\n\
+                      # branch.ab +1 -7\n\
+                      1 M. N... 100644 100644 100644 1 2 staged\n\
+                      1 .M N... 100644 100644 100644 1 2 changed file\n\
+                      1 .T N... 100644 100644 120000 1 2 typechanged\n\
+                      1 D. N... 100644 000000 000000 1 2 deleted\n\
+                      2 RM N... 100644 100644 100644 1 2 R100 new\told\n\
+                      1 .M SC.. 160000 160000 160000 1 2 submodule\n\
+                      u UU N... 100644 100644 100644 100644 1 2 3 conflict\n\
+                      ? untracked\n\
+                      ! ignored\n\
+                      x";
+        let lines: Vec<&str> = output.lines().collect();
+
+        let info = parse_git_branch_info(&lines).unwrap();
+        assert_eq!((info.local.as_str(), info.remote.as_str(), info.ahead, info.behind), ("main", "origin/main", 1, 7));
+
+        let stats = parse_git_stats(&lines);
+        assert_eq!((stats.staged, stats.changed, stats.deleted, stats.renamed), (1, 3, 1, 1));
+        assert_eq!((stats.submodules, stats.conflicted, stats.new), (1, 1, 1));
+
+        assert!(parse_git_branch_info(&["# branch.oid 1234", "# branch.head (detached)"]).is_none());
+    }
 
-        // let input = "## master...origin/master [ahead 1]";
-        let input = "## master...origin/master [ahead 1, behind 7]";
+    #[test]
+    fn test_operation() {
+        let dir = env::temp_dir().join(format!("prompter-operation-test-{}", std::process::id()));
+        let git_dir = dir.join("main.git");
+        let worktree = dir.join("worktree");
+        fs::create_dir_all(git_dir.join("rebase-merge")).unwrap();
+        fs::create_dir_all(worktree.join("src")).unwrap();
 
-        println!("Processing: |{}|", input);
-        
-        let re = Regex::new(
-            r###"^## (?P<local>\S+?)(\.{3}(?P<remote>\S+?)( \[(ahead (?P<ahead>\d+)(, )?)?(behind (?P<behind>\d+))?\])?)?$"###
-            ).expect("Regex could not be parsed.");
+        fs::write(worktree.join(".git"), "gitdir: ../main.git\n").unwrap();
+        assert_eq!(find_git_dir(&worktree.join("src")), Some(worktree.join("../main.git")));
 
-        println!("Found : {}", re.is_match(input));
+        assert_eq!(read_operation(&git_dir), Some(String::from("REBASE")));
 
-        let caps = re.captures(input).unwrap();
+        fs::write(git_dir.join("rebase-merge").join("msgnum"), "3\n").unwrap();
+        fs::write(git_dir.join("rebase-merge").join("end"), "7\n").unwrap();
+        assert_eq!(read_operation(&git_dir), Some(String::from("REBASE 3/7")));
 
-        println!("Len: {}", caps.len());
-        
-        println!("local  : {}", caps.name("local").unwrap().as_str());
-        println!("remote : {}", caps.name("remote").unwrap().as_str());
-        println!("ahead  : {}", caps.name("ahead").unwrap().as_str());
-        println!("behind : {}", caps.name("behind").unwrap().as_str());
-        
+        fs::remove_dir_all(git_dir.join("rebase-merge")).unwrap();
+        assert_eq!(read_operation(&git_dir), None);
+
+        fs::write(git_dir.join("CHERRY_PICK_HEAD"), "").unwrap();
+        assert_eq!(read_operation(&git_dir), Some(String::from("CHERRY-PICK")));
+
+        // stashes of a linked worktree are in the common directory
+        let linked = git_dir.join("worktrees").join("linked");
+        fs::create_dir_all(&linked).unwrap();
+        fs::write(linked.join("commondir"), "../..\n").unwrap();
+        assert_eq!(count_stashes(&linked), 0);
+
+        fs::create_dir_all(git_dir.join("logs").join("refs")).unwrap();
+        fs::write(git_dir.join("logs").join("refs").join("stash"), "0 1 a <a> 1 +0000\tWIP\n1 2 a <a> 2 +0000\tWIP\n").unwrap();
+        assert_eq!(count_stashes(&linked), 2);
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
-}    
+    #[test]
+    fn test_vcs_options() {
+        let config: ConfigMap = serde_json::from_str(r#"{"vcs": {"show_symbol": true, "untracked": false, "parts": ["ahead", "stash"],
+                                                                 "priority": 95, "bg_color": 24},
+                                                        "git": {"show_symbol": false, "priority": 10, "fg_color": 7}}"#).unwrap();
+        let options = json!({"type": "git", "untracked": true});
+
+        let segment = GitSegment {options: options.as_object().cloned(), global_config: get_cwd_config(&config, "git"), cwd: PathBuf::new()};
+        assert!(segment.get_bool("show_symbol", false));
+        assert!(segment.get_bool("untracked", true));
+        assert!(segment.shows("stash"));
+        assert!(!segment.shows("new"));
+
+        // colors and priority come from the same blocks, vcs wins over git
+        assert_eq!(get_priority(&config, "git", None), 95);
+        assert_eq!(get_color_override(&config, "git", None).bg, Some(24));
+        assert_eq!(get_color_override(&config, "git", None).fg, Some(7));
+
+        // a block which is no object is left out
+        let config: ConfigMap = serde_json::from_str(r#"{"vcs": true, "git": {"untracked": false}}"#).unwrap();
+        assert_eq!(get_cwd_config(&config, "git").and_then(|options| options.get("untracked").cloned()), Some(json!(false)));
+
+        let backend = |name: &str| GitSegment {options: json!({"backend": name}).as_object().cloned(), global_config: None, cwd: PathBuf::new()};
+        assert!(backend("native").native() && !backend("command").native());
+
+        // libgit2 unless a daemon caches the output of git, none runs for the tests
+        let segment = GitSegment {options: None, global_config: None, cwd: PathBuf::new()};
+        assert!(segment.native());
+        assert!(!segment.get_bool("show_symbol", false));
+        assert!(segment.shows("operation"));
+    }
 
+}    
@@ -0,0 +1,195 @@
+// Reads the status of a repository with libgit2 instead of running git: HEAD,
+// the refs, the index and the stat data of the worktree. The results are the
+// ones of `git status --porcelain=v2 --branch` and `git describe --tags --always`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use git2::{Branch, DescribeFormatOptions, DescribeOptions, ErrorCode, Repository, Status, StatusOptions};
+use segments::git::{BranchInfo, RepoStats};
+
+// the branch, or the tag or hash of a detached HEAD, and the stats; None outside a worktree
+pub fn read_status(dir: &Path, untracked: bool) -> Result<Option<(String, RepoStats)>, git2::Error> {
+    let repo = match Repository::discover(dir) {
+        Ok(repo) => repo,
+        Err(ref err) if err.code() == ErrorCode::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    // git status refuses to run there
+    if repo.is_bare() {
+        return Ok(None);
+    }
+
+    let mut stats = read_stats(&repo, untracked)?;
+
+    let branch = match read_branch_info(&repo)? {
+        Some(info) => {
+            stats.ahead = info.ahead;
+            stats.behind = info.behind;
+            info.local
+        },
+        None => describe(&repo),
+    };
+    return Ok(Some((branch, stats)));
+}
+
+// None for a detached HEAD, like the porcelain headers
+fn read_branch_info(repo: &Repository) -> Result<Option<BranchInfo>, git2::Error> {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(ref err) if err.code() == ErrorCode::UnbornBranch => {
+            // no commits yet, HEAD names the branch anyway
+            let head = repo.find_reference("HEAD")?;
+            let local = head.symbolic_target().unwrap_or_default().trim_start_matches("refs/heads/").to_string();
+
+            return Ok(Some(BranchInfo {local, remote: String::new(), ahead: 0, behind: 0}));
+        },
+        Err(err) => return Err(err),
+    };
+
+    if !head.is_branch() {
+        return Ok(None);
+    }
+
+    let local = head.shorthand().unwrap_or_default().to_string();
+    let branch = Branch::wrap(head);
+
+    // no upstream, or one that is gone
+    let upstream = match branch.upstream() {
+        Ok(upstream) => upstream,
+        Err(_) => return Ok(Some(BranchInfo {local, remote: String::new(), ahead: 0, behind: 0})),
+    };
+
+    let remote = upstream.name()?.unwrap_or_default().to_string();
+
+    let (ahead, behind) = match (branch.get().target(), upstream.get().target()) {
+        (Some(local_oid), Some(upstream_oid)) => repo.graph_ahead_behind(local_oid, upstream_oid)?,
+        _ => (0, 0),
+    };
+    return Ok(Some(BranchInfo {local, remote, ahead: ahead as i32, behind: behind as i32}));
+}
+
+fn describe(repo: &Repository) -> String {
+    let description = repo.describe(DescribeOptions::new().describe_tags().show_commit_oid_as_fallback(true))
+        .and_then(|description| description.format(Some(DescribeFormatOptions::new().abbreviated_size(7))));
+
+    match description {
+        Ok(name) => name,
+        Err(_) => String::from("Big Bang"),
+    }
+}
+
+fn read_stats(repo: &Repository, untracked: bool) -> Result<RepoStats, git2::Error> {
+    let mut options = StatusOptions::new();
+
+    // untracked directories count once, renames in the index as well
+    options.include_untracked(untracked)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false)
+        .renames_head_to_index(true);
+
+    let mut stats = RepoStats::default();
+
+    let submodules: HashSet<_> = repo.submodules()?.iter().map(|submodule| submodule.path().to_path_buf()).collect();
+
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+
+        if status.is_conflicted() {
+            stats.conflicted += 1;
+            continue;
+        }
+
+        if status == Status::WT_NEW {
+            stats.new += 1;
+            continue;
+        }
+
+        // the XY letters of porcelain v2
+        let index = if status.contains(Status::INDEX_RENAMED) {
+            'R'
+        } else if status.contains(Status::INDEX_DELETED) {
+            'D'
+        } else if status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_TYPECHANGE) {
+            'M'
+        } else {
+            '.'
+        };
+
+        let worktree = if status.contains(Status::WT_DELETED) {
+            'D'
+        } else if status.intersects(Status::WT_MODIFIED | Status::WT_RENAMED | Status::WT_TYPECHANGE) {
+            'M'
+        } else {
+            '.'
+        };
+
+        let dirty_submodule = worktree != '.' && entry.path().is_some_and(|path| submodules.contains(Path::new(path)));
+
+        stats.count(index, worktree, dirty_submodule);
+    }
+    return Ok(stats);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use git2::{RepositoryInitOptions, Signature};
+
+    #[test]
+    fn test_native_status() {
+        let dir = env::temp_dir().join(format!("prompter-git-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let repo = Repository::init_opts(&dir, RepositoryInitOptions::new().initial_head("main")).unwrap();
+
+        // no commits yet
+        fs::write(dir.join("a"), "a").unwrap();
+        let (branch, stats) = read_status(&dir, true).unwrap().unwrap();
+        assert_eq!((branch.as_str(), stats.new), ("main", 1));
+
+        fs::write(dir.join("x"), "x").unwrap();
+        fs::write(dir.join("r"), "renamed").unwrap();
+
+        let mut index = repo.index().unwrap();
+        for file in ["a", "x", "r"] {
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("prompter", "prompter@example.com").unwrap();
+        let commit = repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+
+        // a changed, b staged, r renamed, x deleted, c and d/ untracked
+        fs::write(dir.join("a"), "changed").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+        fs::rename(dir.join("r"), dir.join("s")).unwrap();
+        fs::remove_file(dir.join("x")).unwrap();
+        index.add_path(Path::new("b")).unwrap();
+        index.add_path(Path::new("s")).unwrap();
+        index.remove_path(Path::new("r")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("c"), "c").unwrap();
+        fs::create_dir(dir.join("d")).unwrap();
+        fs::write(dir.join("d").join("e"), "e").unwrap();
+
+        let (branch, stats) = read_status(&dir.join("d"), true).unwrap().unwrap();
+        assert_eq!(branch, "main");
+        assert_eq!((stats.changed, stats.staged, stats.new, stats.conflicted), (1, 1, 2, 0));
+        assert_eq!((stats.renamed, stats.deleted, stats.submodules), (1, 1, 0));
+
+        let (_, stats) = read_status(&dir, false).unwrap().unwrap();
+        assert_eq!((stats.changed, stats.new), (1, 0));
+
+        repo.set_head_detached(commit).unwrap();
+        let (branch, _) = read_status(&dir, true).unwrap().unwrap();
+        assert_eq!(branch, commit.to_string()[..7]);
+
+        assert!(read_status(Path::new("/"), true).unwrap().is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
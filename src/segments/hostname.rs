@@ -2,16 +2,24 @@ use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
 use themes::*;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct HostnameSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>
 }
 
 impl Segment for HostnameSegment {
     fn compute(&self, prompt: &Prompt) -> ResultSet {
-        return (String::from(" \\h "), prompt.theme[HOSTNAME_FG], prompt.theme[HOSTNAME_BG], String::new(), prompt.theme[RESET]);
+        return vec![Part::new("hostname", Content::Hostname, prompt.theme[HOSTNAME_FG], prompt.theme[HOSTNAME_BG])];
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "hostname",
+                                   description: "Hostname of the machine, up to the first dot",
+                                   options: &[]},
+                      |options, _, _| Box::new(HostnameSegment {options}));
+}
@@ -5,9 +5,11 @@ use std::process::Command;
 use themes::*;
 use std::os::unix::process::parent_id;
 use std::str;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 pub struct JobsSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>
 }
 
@@ -23,10 +25,7 @@ impl Segment for JobsSegment {
             .output()
             .expect("Failed to execute git.");
 
-        let std_out_value = match str::from_utf8(&output.stdout){
-                Ok(val) => val,
-                Err(_) => "",
-            };
+        let std_out_value = str::from_utf8(&output.stdout).unwrap_or_default();
         
         let mut counter = 0;
         
@@ -42,10 +41,16 @@ impl Segment for JobsSegment {
         counter -= 1;
 
         if counter > 0 {
-            return (String::from(format!(" {} ", counter )), prompt.theme[JOBS_FG], prompt.theme[JOBS_BG], String::new(), prompt.theme[RESET]);
+            return vec![Part::new("jobs", Content::Text(counter.to_string()), prompt.theme[JOBS_FG], prompt.theme[JOBS_BG])];
         } else {
-            return (String::from(""), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
+            return Vec::new();
         }
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "jobs",
+                                   description: "Number of background jobs of the shell",
+                                   options: &[]},
+                      |options, _, _| Box::new(JobsSegment {options}));
+}
@@ -2,6 +2,7 @@ pub mod env;
 pub mod cwd;
 pub mod username;
 pub mod git;
+pub mod git_native;
 pub mod hostname;
 pub mod dummy;
 pub mod virtual_env;
@@ -11,3 +12,29 @@ pub mod exit_code;
 pub mod stdout;
 pub mod jobs;
 pub mod root;
+pub mod time;
+pub mod newline;
+pub mod plugin;
+pub mod script;
+pub mod registry;
+
+use segments::registry::Registry;
+
+pub fn register_builtins(registry: &mut Registry) {
+    env::register(registry);
+    cwd::register(registry);
+    username::register(registry);
+    git::register(registry);
+    hostname::register(registry);
+    virtual_env::register(registry);
+    read_only::register(registry);
+    ssh::register(registry);
+    exit_code::register(registry);
+    stdout::register(registry);
+    jobs::register(registry);
+    root::register(registry);
+    time::register(registry);
+    newline::register(registry);
+    plugin::register(registry);
+    script::register(registry);
+}
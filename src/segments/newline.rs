@@ -0,0 +1,25 @@
+use serde_json::{Value, Map};
+use Segment;
+use ResultSet;
+use themes::*;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
+
+#[derive(Debug)]
+pub struct NewlineSegment{
+    #[allow(dead_code)]
+    pub options: Option<Map<String, Value>>
+}
+
+impl Segment for NewlineSegment {
+    fn compute(&self, prompt: &Prompt) -> ResultSet {
+        return vec![Part::new("newline", Content::Newline, prompt.theme[RESET], prompt.theme[RESET])];
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "newline",
+                                   description: "Starts a new line of the prompt",
+                                   options: &[]},
+                      |options, _, _| Box::new(NewlineSegment {options}));
+}
@@ -0,0 +1,206 @@
+// Runs an external program as segment. The program gets a JSON object with
+// the context on stdin and answers with the parts to show:
+//
+//   {"cwd": "/home/me", "exit_code": 1, "pipestatus": [0, 1], "shell": "zsh", "options": {...}}
+//
+//   {"visible": true, "parts": [{"text": "prod", "fg": "PATH_FG", "bg": "#800000", "separator": "thin"}]}
+
+use serde_json::{Value, Map};
+use Segment;
+use ResultSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use themes::*;
+use themes::color::reduce;
+use themes::file::color_from_json;
+use prompt::{Prompt, Part, Content, Separator};
+use segments::registry::{Registry, SegmentInfo};
+
+pub struct PluginSegment {
+    pub options: Option<Map<String, Value>>,
+    pub prev_error: i32,
+    pub pipestatus: Vec<i32>,
+    pub cwd: PathBuf,
+}
+
+impl PluginSegment {
+    fn request(&self, prompt: &Prompt, options: &Map<String, Value>) -> Value {
+        json!({
+            "cwd": self.cwd.display().to_string(),
+            "exit_code": self.prev_error,
+            "pipestatus": self.pipestatus,
+            "shell": prompt.shell.name(),
+            "options": options,
+        })
+    }
+}
+
+impl Segment for PluginSegment {
+    fn compute(&self, prompt: &Prompt) -> ResultSet {
+        let options = match self.options {
+            Some(ref options) => options,
+            None => return Vec::new(),
+        };
+
+        let command: Vec<&str> = match options.get("command") {
+            Some(Value::Array(arr)) => arr.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+
+        if command.is_empty() {
+            eprintln!("Plugin segment needs a command");
+            return Vec::new();
+        }
+
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .current_dir(&self.cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn();
+
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => {
+                eprintln!("Failed to execute plugin {}: {}", command[0], err);
+                return Vec::new();
+            }
+        };
+
+        // a plugin not interested in the context may exit before reading it
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(self.request(prompt, options).to_string().as_bytes());
+        }
+
+        let output = match child.wait_with_output() {
+            Ok(output) => output,
+            Err(err) => {
+                eprintln!("Plugin {} failed: {}", command[0], err);
+                return Vec::new();
+            }
+        };
+
+        if !output.status.success() {
+            eprintln!("Plugin {} failed: {}", command[0], output.status);
+            return Vec::new();
+        }
+
+        match parse_response(&String::from_utf8_lossy(&output.stdout), prompt) {
+            Ok(parts) => parts,
+            Err(reason) => {
+                eprintln!("Plugin {}: {}", command[0], reason);
+                Vec::new()
+            }
+        }
+    }
+}
+
+// The colors of a part are theme keys (e.g. "GIT_AHEAD_BG") or color codes,
+// both reduced to the color depth of the terminal.
+fn part_color(value: Option<&Value>, default: Colorcode, prompt: &Prompt) -> Colorcode {
+    let value = match value {
+        Some(value) => value,
+        None => return default,
+    };
+
+    if let Value::String(key) = value {
+        if let Some((_, slot)) = THEME_KEYS.iter().find(|(name, _)| *name == key.to_uppercase()) {
+            return prompt.theme[*slot];
+        }
+    }
+
+    match color_from_json(value) {
+        Some(code) => reduce(code, prompt.depth),
+        None => {
+            eprintln!("Color of a part is neither a theme key nor a color code: {}", value);
+            default
+        }
+    }
+}
+
+pub fn parse_response(response: &str, prompt: &Prompt) -> Result<ResultSet, String> {
+    let response: Map<String, Value> = serde_json::from_str(response).map_err(|err| format!("invalid response: {}", err))?;
+
+    if let Some(Value::Bool(false)) = response.get("visible") {
+        return Ok(Vec::new());
+    }
+
+    match response.get("parts") {
+        Some(Value::Array(items)) => parts_from_json(items, "plugin", prompt),
+        _ => Err(String::from("response without parts")),
+    }
+}
+
+// objects with text, fg, bg, name and separator, also used by script segments
+pub fn parts_from_json(items: &[Value], segment: &str, prompt: &Prompt) -> Result<ResultSet, String> {
+    let mut parts = Vec::new();
+
+    for item in items {
+        let text = match item.get("text") {
+            Some(Value::String(text)) => text,
+            _ => return Err(format!("part without text: {}", item)),
+        };
+
+        let fg = part_color(item.get("fg"), prompt.theme[PATH_FG], prompt);
+        let bg = part_color(item.get("bg"), prompt.theme[PATH_BG], prompt);
+
+        let name = match item.get("name") {
+            Some(Value::String(name)) => format!("{}.{}", segment, name),
+            _ => segment.to_string(),
+        };
+
+        let separator = match item.get("separator").and_then(Value::as_str) {
+            Some("thin") => Separator::Thin(prompt.theme[SEPARATOR_FG]),
+            _ => Separator::Full,
+        };
+
+        parts.push(Part::new(&name, Content::text(text), fg, bg).separated(separator));
+    }
+    return Ok(parts);
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "plugin",
+                                   description: "Parts returned as JSON by an external program",
+                                   options: &[("command", "the program and its arguments, all options are passed on to it")]},
+                      |options, _, context| Box::new(PluginSegment {options,
+                                                                    prev_error: context.prev_error,
+                                                                    pipestatus: context.pipestatus.clone(),
+                                                                    cwd: context.cwd.clone()}));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use themes::default::default_theme;
+    use themes::color::ColorDepth;
+    use shell::Shell;
+
+    #[test]
+    fn test_plugin_segment() {
+        let prompt = Prompt {theme: default_theme(),
+                             symbols: get_symbolset_for_name("patched"),
+                             shell: Shell::Zsh,
+                             depth: ColorDepth::Xterm256};
+
+        let parts = parse_response(r##"{"parts": [{"text": "a", "fg": "git_ahead_fg", "bg": "#ff0000"},
+                                                   {"text": "b", "name": "x", "separator": "thin"}]}"##, &prompt).unwrap();
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].fg, prompt.theme[GIT_AHEAD_FG]);
+        assert_eq!(parts[0].bg, 196);
+        assert_eq!(parts[1].name, "plugin.x");
+        assert_eq!(parts[1].bg, prompt.theme[PATH_BG]);
+        assert_eq!(parts[1].separator, Some(Separator::Thin(prompt.theme[SEPARATOR_FG])));
+
+        assert_eq!(parse_response(r#"{"visible": false}"#, &prompt), Ok(Vec::new()));
+        assert!(parse_response("hi", &prompt).is_err());
+
+        // the context arrives on stdin
+        let options = serde_json::from_str(r#"{"type": "plugin", "command": ["sh", "-c", "grep -q '\"shell\":\"zsh\"' && echo '{\"parts\": [{\"text\": \"zsh\"}]}'"]}"#).unwrap();
+        let segment = PluginSegment {options: Some(options), prev_error: 1, pipestatus: vec![1], cwd: std::env::temp_dir()};
+
+        assert_eq!(segment.compute(&prompt)[0].content, Content::text("zsh"));
+    }
+}
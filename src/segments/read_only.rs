@@ -4,44 +4,39 @@ use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
 use themes::*;
-use std::env;
-use prompt::Prompt;
+use std::path::PathBuf;
+use std::os::unix::ffi::OsStrExt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 use std::ffi::CString;
 use std::ffi::CStr;
 use std::os::raw::c_char;
 
 #[derive(Debug)]
 pub struct ReadOnlySegment{
-    pub options: Option<Map<String, Value>>
+    #[allow(dead_code)]
+    pub options: Option<Map<String, Value>>,
+    pub cwd: PathBuf,
 }
 
 impl Segment for ReadOnlySegment {
     fn compute(&self, prompt: &Prompt) -> ResultSet {
 
-        let path;
-        
-        match env::current_dir() {
-            Err(err) => {
-                eprintln!("Could not get current directory: {}", err);
-                return (String::from("*err*"), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
-            },
-            Ok(p) => {
-                path = p.display().to_string();
-            }
-        } 
-
-        let mut result = String::new();
-
-        if libc_access(&CString::new(path).unwrap(), 2) != 0 {
-            result.push_str(&format!(" {} ", prompt.symbols.lock)); 
+        let path = match CString::new(self.cwd.as_os_str().as_bytes()) {
+            Ok(path) if !self.cwd.as_os_str().is_empty() => path,
+            _ => return vec![Part::new("read_only", Content::text("*err*"), prompt.theme[RESET], prompt.theme[RESET])],
+        };
+
+        if libc_access(&path, 2) != 0 {
+            return vec![Part::new("read_only", Content::text(&prompt.symbols.lock), prompt.theme[READONLY_FG], prompt.theme[READONLY_BG])];
         }
-        return (result, prompt.theme[READONLY_FG], prompt.theme[READONLY_BG], String::new(), prompt.theme[RESET]);
+        return Vec::new();
     }
 }
 
 
 fn libc_access(path: &CStr, mode: libc::c_int) -> libc::c_int {
-    extern {
+    extern "C" {
         fn access(path: *const c_char, mode: libc::c_int) -> libc::c_int;
     }
 
@@ -51,3 +46,10 @@ fn libc_access(path: &CStr, mode: libc::c_int) -> libc::c_int {
 }
 
  
+
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "read_only",
+                                   description: "Lock symbol if the current directory is not writable",
+                                   options: &[]},
+                      |options, _, context| Box::new(ReadOnlySegment {options, cwd: context.cwd.clone()}));
+}
@@ -0,0 +1,126 @@
+use serde_json::{Value, Map};
+use std::env;
+use std::path::PathBuf;
+use Segment;
+use ConfigMap;
+use shell::Shell;
+use segments::register_builtins;
+
+// runtime state shared by all segments of a prompt
+#[derive(Clone, Debug)]
+pub struct Context {
+    pub prev_error: i32,
+    pub pipestatus: Vec<i32>,
+    pub shell: Shell,
+    // width of the terminal, the prompt is not fitted without
+    pub columns: Option<usize>,
+    // the directory segments look at and run their commands in
+    pub cwd: PathBuf,
+    // the same directory as shown by cwd, $PWD keeps the symlinks the shell went through
+    pub pwd: PathBuf,
+}
+
+impl Context {
+    // the directories of the process
+    pub fn new(prev_error: i32, pipestatus: &[i32], shell: Shell) -> Context {
+        let cwd = env::current_dir().unwrap_or_else(|err| {
+            eprintln!("Could not get current directory: {}", err);
+            PathBuf::new()
+        });
+        let pwd = env::var_os("PWD").map_or_else(|| cwd.clone(), PathBuf::from);
+
+        Context {prev_error, pipestatus: pipestatus.to_vec(), shell, columns: None, cwd, pwd}
+    }
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::new(0, &[], Shell::default())
+    }
+}
+
+pub struct SegmentInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    // option name and what it does
+    pub options: &'static [(&'static str, &'static str)],
+}
+
+// Creates a segment from its options (the segment object of the config, if
+// any), the whole config and the context.
+pub type SegmentFactory = dyn Fn(Option<Map<String, Value>>, &ConfigMap, &Context) -> Box<dyn Segment>;
+
+pub struct Registry {
+    entries: Vec<(SegmentInfo, Box<SegmentFactory>)>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {entries: Vec::new()}
+    }
+
+    pub fn with_builtins() -> Registry {
+        let mut registry = Registry::new();
+        register_builtins(&mut registry);
+        registry
+    }
+
+    // a segment registered under an existing name replaces the old one
+    pub fn register<F>(&mut self, info: SegmentInfo, factory: F)
+        where F: Fn(Option<Map<String, Value>>, &ConfigMap, &Context) -> Box<dyn Segment> + 'static {
+
+        self.entries.retain(|(existing, _)| existing.name != info.name);
+        self.entries.push((info, Box::new(factory)));
+    }
+
+    pub fn create(&self, name: &str, options: Option<Map<String, Value>>, config: &ConfigMap, context: &Context) -> Result<Box<dyn Segment>, String> {
+        match self.entries.iter().find(|(info, _)| info.name == name.to_lowercase()) {
+            Some((_, factory)) => Ok(factory(options, config, context)),
+            None => Err(format!("Path segment not found: {}, see --list-segments", name)),
+        }
+    }
+
+    // sorted by name
+    pub fn segments(&self) -> Vec<&SegmentInfo> {
+        let mut infos: Vec<&SegmentInfo> = self.entries.iter().map(|(info, _)| info).collect();
+        infos.sort_by_key(|info| info.name);
+        infos
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::with_builtins()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use prompt::Prompt;
+    use ResultSet;
+
+    struct Nothing;
+
+    impl Segment for Nothing {
+        fn compute(&self, _prompt: &Prompt) -> ResultSet {
+            return Vec::new();
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::with_builtins();
+        let config = ConfigMap::new();
+        let context = Context::default();
+
+        assert!(registry.create("CWD", None, &config, &context).is_ok());
+        assert!(registry.create("nothing", None, &config, &context).is_err());
+
+        registry.register(SegmentInfo {name: "nothing", description: "Shows nothing", options: &[]},
+                          |_, _, _| Box::new(Nothing));
+
+        assert!(registry.create("nothing", None, &config, &context).is_ok());
+        assert!(registry.segments().iter().any(|info| info.name == "git"));
+    }
+}
@@ -2,10 +2,12 @@ use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
 use themes::*;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct RootSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>,
     pub prev_error:i32
 }
@@ -20,7 +22,13 @@ impl Segment for RootSegment {
             bg = prompt.theme[CMD_FAILED_BG];
         }
         
-        return (String::from(" \\$ "), fg, bg, String::new(), prompt.theme[RESET]);
+        return vec![Part::new("root", Content::Root, fg, bg)];
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "root",
+                                   description: "Privilege sign, on a red background if the last command failed",
+                                   options: &[]},
+                      |options, _, context| Box::new(RootSegment {options, prev_error: context.prev_error}));
+}
@@ -0,0 +1,167 @@
+// Evaluates a Rhai script as segment, without starting a process. The script
+// sees cwd, exit_code, pipestatus, shell, options and theme, reads environment
+// variables with env("NAME") and returns what to show: a string, a part as
+// map (#{text: "prod", fg: "PATH_FG", bg: theme.CMD_FAILED_BG}), an array of
+// parts or () for nothing.
+
+use serde_json::{Value, Map};
+use Segment;
+use ResultSet;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use rhai::{Engine, Scope, Dynamic, Array};
+use themes::*;
+use config::get_script_dir;
+use prompt::Prompt;
+use segments::plugin::parts_from_json;
+use segments::registry::{Registry, SegmentInfo};
+
+// keeps a runaway script from blocking the prompt
+const MAX_OPERATIONS: u64 = 100_000;
+
+pub struct ScriptSegment {
+    pub options: Option<Map<String, Value>>,
+    pub prev_error: i32,
+    pub pipestatus: Vec<i32>,
+    pub cwd: PathBuf,
+}
+
+impl ScriptSegment {
+    // inline "script" or a "file", relative to the scripts directory of the config
+    fn source(&self, options: &Map<String, Value>) -> Result<String, String> {
+        if let Some(Value::String(script)) = options.get("script") {
+            return Ok(script.clone());
+        }
+
+        let file = match options.get("file") {
+            Some(Value::String(file)) => PathBuf::from(file),
+            _ => return Err(String::from("needs a script or a file")),
+        };
+
+        let path = match get_script_dir() {
+            Some(dir) if file.is_relative() => dir.join(file),
+            _ => file,
+        };
+
+        fs::read_to_string(&path).map_err(|err| format!("unable to read {}: {}", path.display(), err))
+    }
+
+    fn evaluate(&self, prompt: &Prompt) -> Result<ResultSet, String> {
+        let options = match self.options {
+            Some(ref options) => options,
+            None => return Err(String::from("needs a script or a file")),
+        };
+
+        let source = self.source(options)?;
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        // stdout is the prompt
+        engine.on_print(|text| eprintln!("{}", text));
+        engine.on_debug(|text, _, _| eprintln!("{}", text));
+
+        engine.register_fn("env", |name: &str| -> Dynamic {
+            match env::var(name) {
+                Ok(value) => value.into(),
+                Err(_) => Dynamic::UNIT,
+            }
+        });
+
+        let cwd = self.cwd.display().to_string();
+        let pipestatus: Array = self.pipestatus.iter().map(|&status| Dynamic::from(status as i64)).collect();
+
+        let mut theme = rhai::Map::new();
+        for (name, slot) in THEME_KEYS.iter() {
+            theme.insert((*name).into(), Dynamic::from(prompt.theme[*slot] as i64));
+        }
+
+        let mut scope = Scope::new();
+        scope.push_constant("cwd", cwd);
+        scope.push_constant("exit_code", self.prev_error as i64);
+        scope.push_constant("pipestatus", pipestatus);
+        scope.push_constant("shell", prompt.shell.name());
+        scope.push_constant("options", rhai::serde::to_dynamic(options).map_err(|err| err.to_string())?);
+        scope.push_constant("theme", theme);
+
+        let result: Dynamic = engine.eval_with_scope(&mut scope, &source).map_err(|err| err.to_string())?;
+        let result: Value = rhai::serde::from_dynamic(&result).map_err(|err| err.to_string())?;
+
+        let items: Vec<Value> = match result {
+            Value::Null => return Ok(Vec::new()),
+            Value::Array(items) => items,
+            item => vec![item],
+        };
+
+        // plain strings are parts in the default colors
+        let items: Vec<Value> = items.into_iter()
+            .map(|item| match item {
+                Value::String(text) => json!({"text": text}),
+                item => item,
+            })
+            .collect();
+
+        return parts_from_json(&items, "script", prompt);
+    }
+}
+
+impl Segment for ScriptSegment {
+    fn compute(&self, prompt: &Prompt) -> ResultSet {
+        match self.evaluate(prompt) {
+            Ok(parts) => parts,
+            Err(reason) => {
+                eprintln!("Script segment: {}", reason);
+                Vec::new()
+            }
+        }
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "script",
+                                   description: "Parts returned by a Rhai script, evaluated in-process",
+                                   options: &[("script", "the script itself"),
+                                              ("file", "script file, relative to ~/.config/prompter/scripts")]},
+                      |options, _, context| Box::new(ScriptSegment {options,
+                                                                    prev_error: context.prev_error,
+                                                                    pipestatus: context.pipestatus.clone(),
+                                                                    cwd: context.cwd.clone()}));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use themes::default::default_theme;
+    use themes::color::ColorDepth;
+    use shell::Shell;
+    use prompt::Content;
+
+    fn script(source: &str) -> ScriptSegment {
+        let options = json!({"type": "script", "script": source, "label": "rc"});
+        ScriptSegment {options: options.as_object().cloned(), prev_error: 2, pipestatus: vec![0, 2], cwd: PathBuf::from("/golden")}
+    }
+
+    #[test]
+    fn test_script_segment() {
+        let prompt = Prompt {theme: default_theme(),
+                             symbols: get_symbolset_for_name("patched"),
+                             shell: Shell::Fish,
+                             depth: ColorDepth::Xterm256};
+
+        let parts = script(r#"#{text: options.label + " " + exit_code, bg: theme.CMD_FAILED_BG}"#).compute(&prompt);
+        assert_eq!(parts[0].content, Content::text("rc 2"));
+        assert_eq!(parts[0].bg, prompt.theme[CMD_FAILED_BG]);
+        assert_eq!(parts[0].fg, prompt.theme[PATH_FG]);
+
+        let parts = script(r#"[shell, #{text: `${pipestatus.len()}`, name: "pipe", fg: "GIT_AHEAD_FG"}]"#).compute(&prompt);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].content, Content::text("fish"));
+        assert_eq!(parts[1].name, "script.pipe");
+        assert_eq!(parts[1].fg, prompt.theme[GIT_AHEAD_FG]);
+        assert_eq!(script("cwd").compute(&prompt)[0].content, Content::text("/golden"));
+
+        assert!(script(r#"if env("PROMPTER_NO_SUCH_VARIABLE") == () { () } else { "set" }"#).compute(&prompt).is_empty());
+        assert!(script("loop {}").compute(&prompt).is_empty());
+    }
+}
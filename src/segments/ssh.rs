@@ -3,23 +3,28 @@ use Segment;
 use ResultSet;
 use themes::*;
 use std::env;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct SshSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>
 }
 
 impl Segment for SshSegment {
     fn compute(&self, prompt: &Prompt) -> ResultSet {
 
-        let mut result = String::from("");
-        
         if env::var("SSH_CLIENT").is_ok() {
-            result = format!(" {} ", prompt.symbols.network);
+            return vec![Part::new("ssh", Content::text(&prompt.symbols.network), prompt.theme[SSH_FG], prompt.theme[SSH_BG])];
         }
-        
-        return (result, prompt.theme[SSH_FG], prompt.theme[SSH_BG], String::new(), prompt.theme[RESET]);
+        return Vec::new();
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "ssh",
+                                   description: "Network symbol in SSH sessions",
+                                   options: &[]},
+                      |options, _, _| Box::new(SshSegment {options}));
+}
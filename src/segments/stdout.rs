@@ -2,21 +2,39 @@ use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
 use std::process::Command;
+use std::path::PathBuf;
 use themes::*;
 use std::str;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
+use daemon::cached_output;
 
 pub struct StdoutSegment{
-    pub options: Option<Map<String, Value>>
+    pub options: Option<Map<String, Value>>,
+    pub cwd: PathBuf,
 }
 
 impl Segment for StdoutSegment {
     fn compute(&self, prompt: &Prompt) -> ResultSet {
         if let Some(ref options) = self.options {
             if let Value::Array(ref arr) = options["command"] {
-                if arr.len() > 0 {
+                if !arr.is_empty() {
                     if let Value::String(binary) = &arr[0] {
+                        // output of the same command in the same directory, see the daemon
+                        if options.get("cache") == Some(&Value::Bool(true)) {
+                            let command: Vec<&str> = arr.iter().filter_map(Value::as_str).collect();
+
+                            return match cached_output(&self.cwd, &command, &[]) {
+                                Err(_) => {
+                                    eprintln!("Failed to execute command: {}", binary);
+                                    Vec::new()
+                                },
+                                Ok(output) => vec![Part::new("stdout", Content::text(output.stdout.trim_end()), prompt.theme[PATH_FG], prompt.theme[PATH_BG])],
+                            };
+                        }
+
                         let mut cmd = Command::new(binary);
+                        cmd.current_dir(&self.cwd);
 
                         for arg in &arr[1..] {
                             if let Value::String(a) = arg {
@@ -27,23 +45,27 @@ impl Segment for StdoutSegment {
                         match cmd.output() {
                             Err(_) => {
                                 eprintln!("Failed to execute command: {}", binary);
-                                return (String::new(), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
+                                return Vec::new();
                             },
                             Ok(output) => {
-                                let std_out_value = match str::from_utf8(&output.stdout){
-                                    Ok(val) => val,
-                                    Err(_) => "",
-                                };
+                                let std_out_value = str::from_utf8(&output.stdout).unwrap_or_default();
 
                                 
-                                return (format!(" {} ", String::from(std_out_value.trim_end())), prompt.theme[PATH_FG], prompt.theme[PATH_BG], String::new(), prompt.theme[RESET]);
+                                return vec![Part::new("stdout", Content::text(std_out_value.trim_end()), prompt.theme[PATH_FG], prompt.theme[PATH_BG])];
                             }
                         }
                     }
                 }
             }
         }
-        return (String::new(), prompt.theme[RESET], prompt.theme[RESET], String::new(), prompt.theme[RESET]);
+        return Vec::new();
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "stdout",
+                                   description: "Output of a command",
+                                   options: &[("command", "the command and its arguments, e.g. [\"echo\", \"hi\"]"),
+                                              ("cache", "if true, the output is cached by prompter daemon until the directory changes")]},
+                      |options, _, context| Box::new(StdoutSegment {options, cwd: context.cwd.clone()}));
+}
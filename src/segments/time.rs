@@ -0,0 +1,25 @@
+use serde_json::{Value, Map};
+use Segment;
+use ResultSet;
+use themes::*;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
+
+#[derive(Debug)]
+pub struct TimeSegment{
+    #[allow(dead_code)]
+    pub options: Option<Map<String, Value>>
+}
+
+impl Segment for TimeSegment {
+    fn compute(&self, prompt: &Prompt) -> ResultSet {
+        return vec![Part::new("time", Content::Time, prompt.theme[TIME_FG], prompt.theme[TIME_BG])];
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "time",
+                                   description: "Current time as HH:MM:SS",
+                                   options: &[]},
+                      |options, _, _| Box::new(TimeSegment {options}));
+}
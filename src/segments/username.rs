@@ -2,10 +2,12 @@ use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
 use themes::*;
-use prompt::Prompt;
+use prompt::{Prompt, Part, Content};
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct UsernameSegment {
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>
 }
 
@@ -18,8 +20,13 @@ impl Segment for UsernameSegment {
         if whoami::username() == "root" {
             bgcolor = prompt.theme[USERNAME_ROOT_BG];
         }
-        return (String::from(" \\u "), prompt.theme[USERNAME_FG], bgcolor, String::new(), prompt.theme[RESET]);
+        return vec![Part::new("username", Content::Username, prompt.theme[USERNAME_FG], bgcolor)];
     }
 }
 
-
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "username",
+                                   description: "Name of the user, on a red background for root",
+                                   options: &[]},
+                      |options, _, _| Box::new(UsernameSegment {options}));
+}
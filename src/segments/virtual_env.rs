@@ -1,17 +1,24 @@
 use serde_json::{Value, Map};
 use Segment;
 use ResultSet;
-use themes::*;
 use prompt::Prompt;
+use segments::registry::{Registry, SegmentInfo};
 
 #[derive(Debug)]
 pub struct VirtualEnvSegment{
+    #[allow(dead_code)]
     pub options: Option<Map<String, Value>>
 }
 
 impl Segment for VirtualEnvSegment {
-    fn compute(&self, prompt: &Prompt) -> ResultSet {
-        return (String::from(""), prompt.theme[HOSTNAME_FG], prompt.theme[HOSTNAME_BG], String::new(), prompt.theme[RESET]);
+    fn compute(&self, _prompt: &Prompt) -> ResultSet {
+        return Vec::new();
     }
 }
 
+pub fn register(registry: &mut Registry) {
+    registry.register(SegmentInfo {name: "virtual_env",
+                                   description: "Python virtual environment, not implemented yet",
+                                   options: &[]},
+                      |options, _, _| Box::new(VirtualEnvSegment {options}));
+}
@@ -0,0 +1,225 @@
+// The shell dialect decides how escape sequences are marked as zero-width
+// and how the shell expands hostname, username and the privilege sign.
+
+extern crate libc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Shell {
+    #[default]
+    Bash,
+    Zsh,
+    Fish,
+    Tcsh,
+    Ksh,
+    // plain ANSI for tmux, scripts and echo -e, no shell involved
+    Bare,
+}
+
+pub const SHELL_NAMES: [&str; 6] = ["bash", "zsh", "fish", "tcsh", "ksh", "bare"];
+
+// mksh treats the first character of PS1 as delimiter for non-printing
+// sequences if it is followed by a carriage return
+const KSH_DELIMITER: char = '\x01';
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name.to_lowercase().as_ref() {
+            "bash" => Some(Shell::Bash),
+            "zsh"  => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "tcsh" => Some(Shell::Tcsh),
+            "ksh" | "mksh" => Some(Shell::Ksh),
+            "bare" => Some(Shell::Bare),
+            _      => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh  => "zsh",
+            Shell::Fish => "fish",
+            Shell::Tcsh => "tcsh",
+            Shell::Ksh  => "ksh",
+            Shell::Bare => "bare",
+        }
+    }
+
+    // wrap SGR parameters (e.g. "38;5;250") into a zero-width color sequence
+    pub fn color(self, sgr: &str) -> String {
+        match self {
+            Shell::Bash => format!("\\[\\e[{}m\\]", sgr),
+            Shell::Zsh  => format!("%{{\x1B[{}m%}}", sgr),
+            Shell::Fish | Shell::Bare => format!("\x1B[{}m", sgr),
+            Shell::Tcsh => format!("%{{\x1B[{}m%}}", sgr),
+            Shell::Ksh  => format!("{}\x1B[{}m{}", KSH_DELIMITER, sgr, KSH_DELIMITER),
+        }
+    }
+
+    // emitted once in front of the whole prompt
+    pub fn start(self) -> String {
+        match self {
+            Shell::Ksh => format!("{}\r", KSH_DELIMITER),
+            _          => String::new(),
+        }
+    }
+
+    pub fn hostname(self) -> String {
+        match self {
+            Shell::Bash => String::from("\\h"),
+            Shell::Zsh  => String::from("%m"),
+            Shell::Fish | Shell::Ksh | Shell::Bare => short_hostname(),
+            Shell::Tcsh => String::from("%m"),
+        }
+    }
+
+    pub fn username(self) -> String {
+        match self {
+            Shell::Bash => String::from("\\u"),
+            Shell::Zsh  => String::from("%n"),
+            Shell::Fish | Shell::Ksh | Shell::Bare => whoami::username(),
+            Shell::Tcsh => String::from("%n"),
+        }
+    }
+
+    // '$' for normal users, '#' for root (tcsh uses '>' instead of '$')
+    pub fn root(self) -> String {
+        match self {
+            Shell::Bash => String::from("\\$"),
+            Shell::Zsh | Shell::Tcsh => String::from("%#"),
+            Shell::Fish | Shell::Ksh | Shell::Bare => privilege_sign(),
+        }
+    }
+
+    // the current time as HH:MM:SS
+    pub fn time(self) -> String {
+        match self {
+            Shell::Bash => String::from("\\t"),
+            Shell::Zsh  => String::from("%*"),
+            Shell::Fish | Shell::Ksh | Shell::Bare => local_time(),
+            Shell::Tcsh => String::from("%P"),
+        }
+    }
+
+    // make arbitrary text (paths, branch names, command output) safe
+    // against the prompt expansion of the shell
+    pub fn escape(self, text: &str) -> String {
+        match self {
+            // bash decodes the backslash escapes of PS1 first and expands
+            // parameters and commands in the result (promptvars), a plain
+            // \$ would turn into '#' for root
+            Shell::Bash => text.replace('\\', "\\\\\\\\").replace('$', "\\\\$").replace('`', "\\\\`"),
+            // zsh expands parameters and commands first with PROMPT_SUBST
+            Shell::Zsh  => text.replace('\\', "\\\\").replace('$', "\\$").replace('`', "\\`").replace('%', "%%"),
+            Shell::Fish | Shell::Bare => String::from(text),
+            Shell::Tcsh => text.replace('%', "%%").replace('!', "\\!"),
+            // mksh substitutes parameters and commands like in double quotes
+            // and replaces '!' by the history number afterwards
+            Shell::Ksh  => text.replace('\\', "\\\\").replace('$', "\\$").replace('`', "\\`").replace('"', "\\\"").replace('!', "!!"),
+        }
+    }
+
+}
+
+// Shells without prompt expansion (and the bare output) get the literal
+// values bash would produce for \h, \u and \$.
+
+// hostname up to the first '.'
+fn short_hostname() -> String {
+    let mut buf = [0u8; 256];
+
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+
+    if ret != 0 {
+        eprintln!("Could not get hostname.");
+        return String::from("localhost");
+    }
+
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let hostname = String::from_utf8_lossy(&buf[..len]);
+
+    return hostname.split('.').next().unwrap_or_default().to_string();
+}
+
+fn local_time() -> String {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        eprintln!("Could not get local time.");
+        return String::from("--:--:--");
+    }
+
+    return format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec);
+}
+
+fn privilege_sign() -> String {
+    if unsafe { libc::geteuid() } == 0 {
+        String::from("#")
+    } else {
+        String::from("$")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shell_lookup() {
+        assert_eq!(Shell::from_name("ZSH"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_name("bash"), Some(Shell::Bash));
+        assert_eq!(Shell::from_name("mksh"), Some(Shell::Ksh));
+        assert_eq!(Shell::from_name("cmd.exe"), None);
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(Shell::Bash.escape("C:\\temp"), "C:\\\\\\\\temp");
+        assert_eq!(Shell::Bash.escape("$(id)`id`"), r"\\$(id)\\`id\\`");
+        assert_eq!(Shell::Zsh.escape("100%"), "100%%");
+        assert_eq!(Shell::Zsh.escape(r"\$(id)`id`"), r"\\\$(id)\`id\`");
+        assert_eq!(Shell::Zsh.color("0"), "%{\x1B[0m%}");
+        assert_eq!(Shell::Fish.color("0"), "\x1B[0m");
+        assert_eq!(Shell::Tcsh.escape("50% !"), "50%% \\!");
+        assert_eq!(Shell::Ksh.escape("wow!"), "wow!!");
+        assert_eq!(Shell::Ksh.escape(r#"$(id) "`id`"\"#), r#"\$(id) \"\`id\`\"\\"#);
+        assert_eq!(Shell::Ksh.color("0"), "\x01\x1B[0m\x01");
+    }
+
+    #[test]
+    fn test_bash_prompt_expansion() {
+        // ${PROMPT@P} expands like PS1, a directory named like this must not run a command
+        let dir = "/tmp/inj/$(echo PWNED)/`echo PWNED`/$HOME/C:\\temp\\$";
+
+        let output = std::process::Command::new("bash")
+            .args(["--norc", "-c", "printf '%s' \"${PROMPT@P}\""])
+            .env("PROMPT", Shell::Bash.escape(dir))
+            .output().expect("No bash.");
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), dir);
+    }
+
+    #[test]
+    fn test_ksh_substitution() {
+        // the README setup substitutes the output once, as mksh does for PS1
+        let dir = "/tmp/inj/$(echo PWNED)/`echo PWNED`/\"$HOME\"/C:\\temp\\$";
+
+        let output = std::process::Command::new("sh")
+            .args(["-c", "eval \"printf '%s' \\\"$PROMPT\\\"\""])
+            .env("PROMPT", Shell::Ksh.escape(dir))
+            .output().expect("No sh.");
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), dir);
+    }
+
+    #[test]
+    fn test_literal_expansion() {
+        let hostname = Shell::Fish.hostname();
+
+        assert!(!hostname.is_empty());
+        assert!(!hostname.contains('.'));
+        assert_eq!(Shell::Fish.username(), whoami::username());
+        assert_eq!(Shell::Bare.time().len(), 8);
+    }
+}
@@ -0,0 +1,245 @@
+use std::env;
+use themes::Colorcode;
+
+// 24-bit colors are stored as 0x01rrggbb, above the range of xterm-256 codes,
+// so themes stay plain arrays of color codes. The same goes for colors reduced
+// to the 16 color ANSI palette (0x020000nn) and for dropped colors.
+const TRUECOLOR: Colorcode = 0x0100_0000;
+const ANSI16: Colorcode    = 0x0200_0000;
+pub const COLORLESS: Colorcode = 0x0300_0000;
+
+// levels of the 6x6x6 color cube of xterm-256, codes 16 - 231
+const CUBE_LEVELS: [i32; 6] = [0, 95, 135, 175, 215, 255];
+
+// xterm's defaults for the 16 ANSI colors
+const ANSI_PALETTE: [(i32, i32, i32); 16] = [
+    (0, 0, 0),       (205, 0, 0),     (0, 205, 0),     (205, 205, 0),
+    (0, 0, 238),     (205, 0, 205),   (0, 205, 205),   (229, 229, 229),
+    (127, 127, 127), (255, 0, 0),     (0, 255, 0),     (255, 255, 0),
+    (92, 92, 255),   (255, 0, 255),   (0, 255, 255),   (255, 255, 255),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorDepth {
+    TrueColor,
+    Xterm256,
+    Ansi16,
+    Monochrome,
+}
+
+pub const COLOR_DEPTH_NAMES: [&str; 4] = ["truecolor", "256", "16", "none"];
+
+impl ColorDepth {
+    pub fn from_name(name: &str) -> Option<ColorDepth> {
+        match name.to_lowercase().as_ref() {
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256"                 => Some(ColorDepth::Xterm256),
+            "16"                  => Some(ColorDepth::Ansi16),
+            "none" | "0"          => Some(ColorDepth::Monochrome),
+            _                     => None,
+        }
+    }
+}
+
+pub fn rgb(r: u8, g: u8, b: u8) -> Colorcode {
+    TRUECOLOR | (r as Colorcode) << 16 | (g as Colorcode) << 8 | b as Colorcode
+}
+
+pub fn is_truecolor(code: Colorcode) -> bool {
+    code & !0x00FF_FFFF == TRUECOLOR
+}
+
+pub fn to_rgb(code: Colorcode) -> (u8, u8, u8) {
+    ((code >> 16 & 0xFF) as u8, (code >> 8 & 0xFF) as u8, (code & 0xFF) as u8)
+}
+
+// accepts xterm-256 codes ("161"), "#rrggbb" and "rgb(r,g,b)"
+pub fn parse_color(spec: &str) -> Option<Colorcode> {
+    let spec = spec.trim().to_lowercase();
+
+    if let Some(hex) = spec.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+
+        return Some(rgb(r, g, b));
+    }
+
+    if let Some(args) = spec.strip_prefix("rgb(").and_then(|rest| rest.strip_suffix(')')) {
+        let values: Vec<u8> = args.split(',').filter_map(|v| v.trim().parse::<u8>().ok()).collect();
+
+        if values.len() != 3 || args.split(',').count() != 3 {
+            return None;
+        }
+        return Some(rgb(values[0], values[1], values[2]));
+    }
+
+    // codes above 255 would be taken for the encoded colors
+    return spec.parse::<u8>().ok().map(Colorcode::from);
+}
+
+pub fn color_depth_from_env() -> ColorDepth {
+    let term = env::var("TERM").ok();
+    let colorterm = env::var("COLORTERM").ok();
+    let no_color = env::var("NO_COLOR").map(|value| !value.is_empty()).unwrap_or(false);
+
+    detect_color_depth(term.as_deref(), colorterm.as_deref(), no_color)
+}
+
+// see https://no-color.org, COLORTERM announces 24-bit support
+pub fn detect_color_depth(term: Option<&str>, colorterm: Option<&str>, no_color: bool) -> ColorDepth {
+    if no_color {
+        return ColorDepth::Monochrome;
+    }
+
+    if let Some(value) = colorterm {
+        if value == "truecolor" || value == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+    }
+
+    match term {
+        Some("dumb") => ColorDepth::Monochrome,
+        Some(t) if t.contains("256color") => ColorDepth::Xterm256,
+        Some(t) if t == "linux" || t == "ansi" || t == "cons25" || t.starts_with("vt") || t.contains("16color") => ColorDepth::Ansi16,
+        _ => ColorDepth::Xterm256,
+    }
+}
+
+pub fn is_ansi16(code: Colorcode) -> bool {
+    code & !0xFF == ANSI16
+}
+
+// the color to emit on a terminal with the given depth
+pub fn reduce(code: Colorcode, depth: ColorDepth) -> Colorcode {
+    // negative codes are no colors, e.g. RESET
+    if code < 0 {
+        return code;
+    }
+
+    // already reduced, e.g. theme colors handed to a script segment
+    if is_ansi16(code) || code == COLORLESS {
+        return code;
+    }
+
+    match depth {
+        ColorDepth::TrueColor  => code,
+        ColorDepth::Xterm256   => to_xterm256(code),
+        ColorDepth::Ansi16     => ANSI16 | to_ansi16(to_xterm256(code)),
+        ColorDepth::Monochrome => COLORLESS,
+    }
+}
+
+fn to_ansi16(code: Colorcode) -> Colorcode {
+    if code < 16 {
+        return code;
+    }
+
+    let target = xterm256_to_rgb(code);
+
+    (0..ANSI_PALETTE.len())
+        .min_by_key(|&idx| distance(ANSI_PALETTE[idx], target))
+        .unwrap_or(0) as Colorcode
+}
+
+fn xterm256_to_rgb(code: Colorcode) -> (i32, i32, i32) {
+    if code >= 232 {
+        let level = 8 + (code.min(255) - 232) * 10;
+        return (level, level, level);
+    }
+
+    let idx = (code - 16) as usize;
+    (CUBE_LEVELS[idx / 36], CUBE_LEVELS[idx / 6 % 6], CUBE_LEVELS[idx % 6])
+}
+
+pub fn to_xterm256(code: Colorcode) -> Colorcode {
+    if !is_truecolor(code) {
+        return code;
+    }
+
+    let (r, g, b) = to_rgb(code);
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+
+    let nearest_level = |value: i32| -> usize {
+        (0..CUBE_LEVELS.len()).min_by_key(|&idx| (CUBE_LEVELS[idx] - value).abs()).unwrap_or(0)
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube_code = 16 + 36 * ri as i32 + 6 * gi as i32 + bi as i32;
+    let cube_distance = distance((r, g, b), (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]));
+
+    // grayscale ramp, codes 232 - 255 with the levels 8, 18, ..., 238
+    let gray_idx = (((r + g + b) / 3 - 8 + 5) / 10).clamp(0, 23);
+    let gray_level = 8 + gray_idx * 10;
+    let gray_distance = distance((r, g, b), (gray_level, gray_level, gray_level));
+
+    if gray_distance < cube_distance {
+        return 232 + gray_idx;
+    }
+    return cube_code;
+}
+
+fn distance(a: (i32, i32, i32), b: (i32, i32, i32)) -> i32 {
+    (a.0 - b.0).pow(2) + (a.1 - b.1).pow(2) + (a.2 - b.2).pow(2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#FF8000"), Some(rgb(255, 128, 0)));
+        assert_eq!(parse_color("rgb(0, 128, 255)"), Some(rgb(0, 128, 255)));
+        assert_eq!(parse_color("161"), Some(161));
+        assert_eq!(parse_color("#ff80"), None);
+        assert_eq!(parse_color("rgb(1,2)"), None);
+        assert_eq!(parse_color("rgb(1,2,300)"), None);
+        assert_eq!(parse_color("255"), Some(255));
+        assert_eq!(parse_color("256"), None);
+        assert_eq!(parse_color("16777215"), None);
+        assert_eq!(parse_color("-5"), None);
+
+        assert!(is_truecolor(rgb(0, 0, 0)));
+        assert!(!is_truecolor(255));
+        assert!(!is_truecolor(-1));
+        assert_eq!(to_rgb(rgb(1, 2, 3)), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_xterm256_fallback() {
+        assert_eq!(to_xterm256(rgb(255, 0, 0)), 196);
+        assert_eq!(to_xterm256(rgb(0, 0, 0)), 16);
+        assert_eq!(to_xterm256(rgb(128, 128, 128)), 244);
+        assert_eq!(to_xterm256(rgb(0, 135, 175)), 31);
+        assert_eq!(to_xterm256(250), 250);
+    }
+
+    #[test]
+    fn test_color_depth_detection() {
+        assert_eq!(detect_color_depth(Some("xterm-256color"), Some("truecolor"), false), ColorDepth::TrueColor);
+        assert_eq!(detect_color_depth(Some("xterm-256color"), None, false), ColorDepth::Xterm256);
+        assert_eq!(detect_color_depth(Some("linux"), None, false), ColorDepth::Ansi16);
+        assert_eq!(detect_color_depth(Some("vt220"), None, false), ColorDepth::Ansi16);
+        assert_eq!(detect_color_depth(Some("dumb"), None, false), ColorDepth::Monochrome);
+        assert_eq!(detect_color_depth(Some("xterm-256color"), Some("truecolor"), true), ColorDepth::Monochrome);
+        assert_eq!(detect_color_depth(None, None, false), ColorDepth::Xterm256);
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(reduce(rgb(255, 0, 0), ColorDepth::Xterm256), 196);
+        assert_eq!(reduce(196, ColorDepth::Ansi16), ANSI16 | 9);
+        assert_eq!(reduce(250, ColorDepth::Ansi16), ANSI16 | 7);
+        assert_eq!(reduce(237, ColorDepth::Ansi16), ANSI16);
+        assert_eq!(reduce(4, ColorDepth::Ansi16), ANSI16 | 4);
+        assert_eq!(reduce(31, ColorDepth::Monochrome), COLORLESS);
+        assert_eq!(reduce(-1, ColorDepth::Monochrome), -1);
+        assert_eq!(reduce(ANSI16 | 9, ColorDepth::Ansi16), ANSI16 | 9);
+        assert!(is_ansi16(reduce(rgb(0, 135, 175), ColorDepth::Ansi16)));
+    }
+}
@@ -73,5 +73,15 @@ pub fn default_theme() -> Theme {
     theme[ TIME_FG] = 250;
     theme[ TIME_BG] = 238;
 
+    theme[ GIT_OPERATION_FG] = 0;
+    theme[ GIT_OPERATION_BG] = 214;  // orange
+
+    theme[ GIT_RENAMED_BG] = 28;
+    theme[ GIT_RENAMED_FG] = 15;
+    theme[ GIT_DELETED_BG] = 88;  // dark red
+    theme[ GIT_DELETED_FG] = 15;
+    theme[ GIT_SUBMODULE_BG] = 60;
+    theme[ GIT_SUBMODULE_FG] = 15;
+
     return theme;
 }
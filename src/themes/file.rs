@@ -0,0 +1,110 @@
+use serde_json::{Value, Map};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use themes::*;
+use themes::default::default_theme;
+use themes::color::parse_color;
+
+/*
+Themes could be loaded from JSON files mapping theme slots to color codes:
+
+{
+    "USERNAME_FG": 250,
+    "GIT_AHEAD_BG": "#585858"
+}
+
+Slots not mentioned in the file keep the value of the default theme.
+*/
+
+pub fn get_theme_file(theme_dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = theme_dir.join(format!("{}.json", name));
+
+    if path.exists() {
+        return Some(path);
+    }
+    return None;
+}
+
+// names of all themes found in theme_dir
+pub fn list_theme_files(theme_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(theme_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+    names.sort();
+    return names;
+}
+
+pub fn load_theme_file(path: &Path) -> Result<Theme, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("Unable to read theme {}: {}", path.display(), err))?;
+
+    match serde_json::from_str(&source) {
+        Ok(map) => Ok(theme_from_json(&map)),
+        Err(err) => Err(format!("Theme file {} broken: {}", path.display(), err)),
+    }
+}
+
+pub fn theme_from_json(map: &Map<String, Value>) -> Theme {
+    let mut theme = default_theme();
+
+    for (key, value) in map {
+        let slot = match THEME_KEYS.iter().find(|(name, _)| *name == key.to_uppercase()) {
+            Some((_, slot)) => *slot,
+            None => {
+                eprintln!("Unknown theme key: {}", key);
+                continue;
+            }
+        };
+
+        match color_from_json(value) {
+            Some(code) => theme[slot] = code,
+            None => eprintln!("Theme key {} needs a color code, got: {}", key, value),
+        }
+    }
+    return theme;
+}
+
+// xterm-256 codes as number, "#rrggbb" and "rgb(r,g,b)" as string
+pub fn color_from_json(value: &Value) -> Option<Colorcode> {
+    match value {
+        // xterm-256 codes, others are invalid, not truncated into another color
+        Value::Number(num) => num.as_u64().and_then(|code| u8::try_from(code).ok()).map(Colorcode::from),
+        Value::String(s) => parse_color(s),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_json() {
+        let map: Map<String, Value> = serde_json::from_str(r##"{ "USERNAME_FG": 1, "git_ahead_bg": 2, "HOSTNAME_FG": "#0080ff", "NO_SUCH_KEY": 3 }"##).unwrap();
+        let theme = theme_from_json(&map);
+
+        assert_eq!(theme[USERNAME_FG], 1);
+        assert_eq!(theme[GIT_AHEAD_BG], 2);
+        assert_eq!(theme[HOSTNAME_FG], color::rgb(0, 128, 255));
+        assert_eq!(theme[HOSTNAME_BG], default_theme()[HOSTNAME_BG]);
+
+        assert_eq!(color_from_json(&json!(255)), Some(255));
+        assert_eq!(color_from_json(&json!("0")), Some(0));
+        assert_eq!(color_from_json(&json!(4294967297i64)), None);
+        assert_eq!(color_from_json(&json!(16777215)), None);
+        assert_eq!(color_from_json(&json!(256)), None);
+        assert_eq!(color_from_json(&json!(-5)), None);
+        assert_eq!(color_from_json(&json!("-5")), None);
+        assert_eq!(color_from_json(&json!(1.5)), None);
+    }
+}
@@ -1,40 +1,80 @@
 pub mod default;
 pub mod crazy;
 pub mod painter;
+pub mod file;
+pub mod color;
 
-pub fn _get_color_templates(s: &str) -> String {
-    match s {
-        "bash" => String::from("\x1B[{}]"),
-        "tcsh" => String::from("%%{\x1B%s%%}"),
-        "zsh"  => String::from("%%{%%}"),
-        "bare" => String::from(""),
-        _      => String::from(""),                
+use themes::default::default_theme;
+use themes::crazy::crazy_theme;
+
+type ThemeConstructor = fn() -> Theme;
+
+// all built-in themes, selected by the "theme" key of the config
+pub const THEMES: [(&str, ThemeConstructor); 2] = [
+    ("default", default_theme),
+    ("crazy",   crazy_theme),
+];
+
+// slots of a theme not holding a color
+const NON_COLOR_SLOTS: [usize; 2] = [RESET, HOME_SPECIAL_DISPLAY];
+
+pub fn reduce_theme(theme: Theme, depth: color::ColorDepth) -> Theme {
+    let mut reduced = theme;
+
+    for (slot, code) in reduced.iter_mut().enumerate() {
+        if !NON_COLOR_SLOTS.contains(&slot) {
+            *code = color::reduce(*code, depth);
+        }
     }
+    return reduced;
+}
+
+pub fn get_theme_for_name(name: &str) -> Option<Theme> {
+    THEMES.iter()
+        .find(|(theme_name, _)| *theme_name == name.to_lowercase())
+        .map(|(_, constructor)| constructor())
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Symbols {
-    pub lock: &'static str,
-    pub network: &'static str,
-    pub separator: &'static str,
-    pub separator_thin: &'static str,
+    pub lock: String,
+    pub network: String,
+    pub vcs: String,
+    pub separator: String,
+    pub separator_thin: String,
+    // pointing to the left, used by the right prompt
+    pub separator_right: String,
+    pub separator_right_thin: String,
+}
+
+impl Symbols {
+    fn new(lock: &str, network: &str, vcs: &str, separators: [&str; 4]) -> Symbols {
+        Symbols {lock: lock.to_string(),
+                 network: network.to_string(),
+                 vcs: vcs.to_string(),
+                 separator: separators[0].to_string(),
+                 separator_thin: separators[1].to_string(),
+                 separator_right: separators[2].to_string(),
+                 separator_right_thin: separators[3].to_string()}
+    }
 }
 
+pub const SYMBOL_MODES: [&str; 3] = ["patched", "compatible", "flat"];
 
 pub fn get_symbolset_for_name(name: &str) -> Symbols {
 
-    let patched = Symbols {lock: "\u{E0A2}", network: "SSH", separator: "\u{E0B0}", separator_thin: "\u{E0B1}" };
+    let patched = Symbols::new("\u{E0A2}", "SSH", "\u{E0A0}", ["\u{E0B0}", "\u{E0B1}", "\u{E0B2}", "\u{E0B3}"]);
 
     match name {
-        "compatible" => Symbols {lock: "RO", network: "SSH", separator: "\u{25B6}", separator_thin: "\u{276F}"},
+        "compatible" => Symbols::new("RO", "SSH", "\u{2387}", ["\u{25B6}", "\u{276F}", "\u{25C0}", "\u{276E}"]),
         "patched"    => patched,
-        "flat"       => Symbols {lock: "\u{E0A2}", network: "SSH", separator: "", separator_thin: ""},        
+        "flat"       => Symbols::new("\u{E0A2}", "SSH", "\u{E0A0}", ["", "", "", ""]),
         _ =>         patched,
     }
 }
 
 
-pub const THEME_SIZE:usize = 53;
+pub const THEME_SIZE:usize = 61;
 
 pub type Colorcode = i32;
 pub type Theme = [Colorcode; THEME_SIZE];
@@ -92,12 +132,85 @@ pub const     AWS_PROFILE_FG:usize = 49;
 pub const     AWS_PROFILE_BG:usize = 50;
 pub const     TIME_FG:usize = 51;
 pub const     TIME_BG:usize = 52;
+pub const     GIT_OPERATION_FG:usize = 53;
+pub const     GIT_OPERATION_BG:usize = 54;
+pub const     GIT_RENAMED_FG:usize = 55;
+pub const     GIT_RENAMED_BG:usize = 56;
+pub const     GIT_DELETED_FG:usize = 57;
+pub const     GIT_DELETED_BG:usize = 58;
+pub const     GIT_SUBMODULE_FG:usize = 59;
+pub const     GIT_SUBMODULE_BG:usize = 60;
+
+// symbolic names of the theme slots, as used in theme files
+pub const THEME_KEYS: [(&str, usize); THEME_SIZE] = [
+    ("RESET",                RESET),
+    ("USERNAME_FG",          USERNAME_FG),
+    ("USERNAME_BG",          USERNAME_BG),
+    ("USERNAME_ROOT_BG",     USERNAME_ROOT_BG),
+    ("HOSTNAME_FG",          HOSTNAME_FG),
+    ("HOSTNAME_BG",          HOSTNAME_BG),
+    ("HOME_SPECIAL_DISPLAY", HOME_SPECIAL_DISPLAY),
+    ("HOME_BG",              HOME_BG),
+    ("HOME_FG",              HOME_FG),
+    ("PATH_BG",              PATH_BG),
+    ("PATH_FG",              PATH_FG),
+    ("CWD_FG",               CWD_FG),
+    ("SEPARATOR_FG",         SEPARATOR_FG),
+    ("READONLY_BG",          READONLY_BG),
+    ("READONLY_FG",          READONLY_FG),
+    ("SSH_BG",               SSH_BG),
+    ("SSH_FG",               SSH_FG),
+    ("REPO_CLEAN_BG",        REPO_CLEAN_BG),
+    ("REPO_CLEAN_FG",        REPO_CLEAN_FG),
+    ("REPO_DIRTY_BG",        REPO_DIRTY_BG),
+    ("REPO_DIRTY_FG",        REPO_DIRTY_FG),
+    ("JOBS_FG",              JOBS_FG),
+    ("JOBS_BG",              JOBS_BG),
+    ("CMD_PASSED_BG",        CMD_PASSED_BG),
+    ("CMD_PASSED_FG",        CMD_PASSED_FG),
+    ("CMD_FAILED_BG",        CMD_FAILED_BG),
+    ("CMD_FAILED_FG",        CMD_FAILED_FG),
+    ("SVN_CHANGES_BG",       SVN_CHANGES_BG),
+    ("SVN_CHANGES_FG",       SVN_CHANGES_FG),
+    ("GIT_AHEAD_BG",         GIT_AHEAD_BG),
+    ("GIT_AHEAD_FG",         GIT_AHEAD_FG),
+    ("GIT_BEHIND_BG",        GIT_BEHIND_BG),
+    ("GIT_BEHIND_FG",        GIT_BEHIND_FG),
+    ("GIT_STAGED_BG",        GIT_STAGED_BG),
+    ("GIT_STAGED_FG",        GIT_STAGED_FG),
+    ("GIT_NOTSTAGED_BG",     GIT_NOTSTAGED_BG),
+    ("GIT_NOTSTAGED_FG",     GIT_NOTSTAGED_FG),
+    ("GIT_UNTRACKED_BG",     GIT_UNTRACKED_BG),
+    ("GIT_UNTRACKED_FG",     GIT_UNTRACKED_FG),
+    ("GIT_CONFLICTED_BG",    GIT_CONFLICTED_BG),
+    ("GIT_CONFLICTED_FG",    GIT_CONFLICTED_FG),
+    ("GIT_STASH_BG",         GIT_STASH_BG),
+    ("GIT_STASH_FG",         GIT_STASH_FG),
+    ("VIRTUAL_ENV_BG",       VIRTUAL_ENV_BG),
+    ("VIRTUAL_ENV_FG",       VIRTUAL_ENV_FG),
+    ("BATTERY_NORMAL_BG",    BATTERY_NORMAL_BG),
+    ("BATTERY_NORMAL_FG",    BATTERY_NORMAL_FG),
+    ("BATTERY_LOW_BG",       BATTERY_LOW_BG),
+    ("BATTERY_LOW_FG",       BATTERY_LOW_FG),
+    ("AWS_PROFILE_FG",       AWS_PROFILE_FG),
+    ("AWS_PROFILE_BG",       AWS_PROFILE_BG),
+    ("TIME_FG",              TIME_FG),
+    ("TIME_BG",              TIME_BG),
+    ("GIT_OPERATION_FG",     GIT_OPERATION_FG),
+    ("GIT_OPERATION_BG",     GIT_OPERATION_BG),
+    ("GIT_RENAMED_FG",       GIT_RENAMED_FG),
+    ("GIT_RENAMED_BG",       GIT_RENAMED_BG),
+    ("GIT_DELETED_FG",       GIT_DELETED_FG),
+    ("GIT_DELETED_BG",       GIT_DELETED_BG),
+    ("GIT_SUBMODULE_FG",     GIT_SUBMODULE_FG),
+    ("GIT_SUBMODULE_BG",     GIT_SUBMODULE_BG),
+];
 
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use themes::default::default_theme;
+    use shell::Shell;
     
     #[test]
     fn test_color_output() {
@@ -116,17 +229,28 @@ mod test {
     #[test]
     fn test_painter() {
         println!("\nTEST: --|{}{}VALUE{}{}{}|--",
-                 painter::fgcolor(250),
-                 painter::bgcolor(240),
-                 painter::bgcolor(238),
-                 painter::fgcolor(240),
-                 painter::reset()
+                 painter::fgcolor(Shell::Bash, 250),
+                 painter::bgcolor(Shell::Bash, 240),
+                 painter::bgcolor(Shell::Bash, 238),
+                 painter::fgcolor(Shell::Bash, 240),
+                 painter::reset(Shell::Bash)
 
         );
 
         let theme = default_theme();
         
         assert!(theme[USERNAME_FG] == 250); // see default theme!
+
+        assert_eq!(painter::fgcolor(Shell::Bash, color::rgb(255, 128, 0)), "\\[\\e[38;2;255;128;0m\\]");
+    }
+
+    #[test]
+    fn test_theme_lookup() {
+        let theme = get_theme_for_name("Crazy").expect("crazy theme not registered");
+        assert!(theme[RESET] == -99);
+
+        assert!(get_theme_for_name("default").is_some());
+        assert!(get_theme_for_name("no-such-theme").is_none());
     }
 
     #[test]
@@ -134,6 +258,7 @@ mod test {
         let symbols = get_symbolset_for_name("flat");
         assert!(symbols.network == "SSH");
         assert!(symbols.lock == "\u{E0A2}");        
+        assert!(get_symbolset_for_name("patched").separator_right == "\u{E0B2}");
     }
 
 }    
@@ -1,23 +1,44 @@
 use themes::Colorcode;
+use themes::color::{is_truecolor, is_ansi16, to_rgb, COLORLESS};
+use shell::Shell;
 
-/* 
-color: \[\e[38;5;250m\]
+/*
+color: \[\e[38;5;250m\]        (bash)
+       %{\e[38;5;250m%}        (zsh)
+       \[\e[38;2;255;128;0m\]  (bash, 24-bit)
+       \[\e[91m\]              (bash, 16 colors)
 
 reset: \[\e[0m\]
 */
 
-pub fn color(prefix: &str, code: Colorcode) -> String {
-    return String::from(format!("\\[\\e[{};5;{}m\\]", prefix, code));
+pub fn color(shell: Shell, prefix: &str, code: Colorcode) -> String {
+    if code == COLORLESS {
+        return String::new();
+    }
+
+    if is_ansi16(code) {
+        // 30-37/90-97 for the foreground, 40-47/100-107 for the background
+        let base = if prefix == "38" { 30 } else { 40 };
+        let idx = code & 0xF;
+        let sgr = if idx < 8 { base + idx } else { base + 60 + idx - 8 };
+        return shell.color(&sgr.to_string());
+    }
+
+    if is_truecolor(code) {
+        let (r, g, b) = to_rgb(code);
+        return shell.color(&format!("{};2;{};{};{}", prefix, r, g, b));
+    }
+    shell.color(&format!("{};5;{}", prefix, code))
 }
 
-pub fn fgcolor(code: Colorcode) -> String {
-    color("38", code)
+pub fn fgcolor(shell: Shell, code: Colorcode) -> String {
+    color(shell, "38", code)
 }
 
-pub fn bgcolor(code: Colorcode) -> String {
-    color("48", code)
+pub fn bgcolor(shell: Shell, code: Colorcode) -> String {
+    color(shell, "48", code)
 }
 
-pub fn reset() -> String {
-    return String::from("\\[\\e[0m\\]");
+pub fn reset(shell: Shell) -> String {
+    shell.color("0")
 }